@@ -0,0 +1,77 @@
+use crate::visitor::FileItems;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+
+// A directory of what was extracted from each source file, so that files which have
+// not changed since the last run need not be parsed again. An entry is only used while
+// the hash of the file's content still matches.
+// Bumped whenever what is extracted from a file changes, e.g. a new kind of call site
+// is recorded, even if `FileItems` keeps its shape. The package version alone stays the
+// same across such changes during development.
+const EXTRACTION_VERSION: u32 = 1;
+
+#[derive(Clone)]
+pub struct Cache {
+    dir: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    // Entries written by another version may have been extracted differently
+    version: String,
+    extraction_version: u32,
+    content_hash: u64,
+    items: FileItems,
+}
+
+impl Cache {
+    pub fn new(dir: PathBuf) -> Self {
+        Cache { dir }
+    }
+
+    pub fn load(&self, key: &str, content: &str) -> Option<FileItems> {
+        let json = fs::read_to_string(self.entry_path(key)).ok()?;
+        let entry: Entry = serde_json::from_str(&json).ok()?;
+
+        match entry.version == env!("CARGO_PKG_VERSION")
+            && entry.extraction_version == EXTRACTION_VERSION
+            && entry.content_hash == hash(content)
+        {
+            true => {
+                log::debug!("Using cached definitions for '{}'", key);
+                Some(entry.items)
+            }
+            false => None,
+        }
+    }
+
+    // Failing to write only costs a re-parse next time, so it is not an error
+    pub fn store(&self, key: &str, content: &str, items: &FileItems) {
+        let entry = Entry {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            extraction_version: EXTRACTION_VERSION,
+            content_hash: hash(content),
+            items: items.clone(),
+        };
+
+        let written = fs::create_dir_all(&self.dir)
+            .map_err(|e| e.to_string())
+            .and_then(|_| serde_json::to_string(&entry).map_err(|e| e.to_string()))
+            .and_then(|json| fs::write(self.entry_path(key), json).map_err(|e| e.to_string()));
+        match written {
+            Ok(_) => (),
+            Err(e) => log::warn!("Failed to cache '{}': {}", key, e),
+        }
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}.json", hash(key)))
+    }
+}
+
+// 64-bit FNV-1a, which unlike `DefaultHasher` is the same from one build to the next
+fn hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
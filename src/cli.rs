@@ -0,0 +1,382 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use ferrisfollow::{
+    graph::{Palette, Theme},
+    workspace::EntryMode,
+};
+use serde::Deserialize;
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelStyle {
+    /// The qualified function name
+    Name,
+    /// The qualified function name followed by its parameters and return type
+    Signature,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SizeBy {
+    /// Lines of code in the function
+    Loc,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorBy {
+    /// Cyclomatic complexity of the function, from green (simple) to red (complex)
+    Complexity,
+    /// How many calls the function is from the nearest entry point, graded with the
+    /// palette in place of the call sequence
+    Depth,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Graphviz DOT source only
+    Dot,
+    /// A PNG image rendered with Graphviz, alongside its DOT source
+    Png,
+    /// An SVG image rendered with Graphviz, whose nodes link to their source
+    Svg,
+    /// A vector PDF rendered with Graphviz, for printing or embedding in documents
+    Pdf,
+    /// A self-contained interactive page with pan/zoom, search and neighbor highlighting
+    Html,
+    /// Nodes with their metadata and edges, following a versioned schema
+    Json,
+    /// JSON Lines: a metadata line, then one line per node and one per edge, written as
+    /// they are built
+    Jsonl,
+    /// Plain text with one `caller -> callee, callee` line per caller, sorted
+    Adjacency,
+}
+
+impl OutputFormat {
+    // Whether the output is an image rendered from the DOT file by Graphviz
+    pub fn is_image(self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Png | OutputFormat::Svg | OutputFormat::Pdf
+        )
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Dot => "dot",
+            OutputFormat::Png => "png",
+            OutputFormat::Svg => "svg",
+            OutputFormat::Pdf => "pdf",
+            OutputFormat::Html => "html",
+            OutputFormat::Json => "json",
+            OutputFormat::Jsonl => "jsonl",
+            OutputFormat::Adjacency => "txt",
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Check calls against architecture rules, exiting non-zero on any violation
+    Check {
+        /// Forbid calls matching `<source> -> <target>`, where both sides are globs
+        /// like `api::*`; may be repeated
+        #[arg(long, value_name = "RULE")]
+        deny: Vec<String>,
+
+        /// Exempt calls matching `<source> -> <target>` from the deny rules; may be repeated
+        #[arg(long, value_name = "RULE")]
+        allow: Vec<String>,
+    },
+    /// Print the callers and callees of one function, without drawing the graph
+    Neighbors {
+        /// The function, qualified as far as needed to tell it apart (e.g. `server::run`)
+        function: String,
+
+        /// Also list functions up to N calls away
+        #[arg(long, value_name = "N", default_value_t = 1)]
+        hops: usize,
+    },
+    /// Print the shortest chain of calls from one function to another
+    Path {
+        /// The calling function, qualified as far as needed to tell it apart
+        from: String,
+
+        /// The function called, qualified as far as needed to tell it apart
+        to: String,
+    },
+}
+
+#[derive(Parser)]
+#[command(version, about = "A way to explore your Rust program visually")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Directory containing the `Cargo.toml` to analyze, or a single `.rs` file
+    #[arg(default_value = ".")]
+    pub path: PathBuf,
+
+    /// Another repository to analyze along with the first, merged into one graph; may
+    /// be repeated. Calls into crates of another repository resolve when they are
+    /// dependencies
+    #[arg(long = "also", value_name = "DIR", conflicts_with = "git_rev")]
+    pub more_paths: Vec<PathBuf>,
+
+    /// Settings file to read, defaults to `ferrisfollow.toml` in the analyzed directory.
+    /// Flags given on the command line take precedence over it
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// What to generate
+    #[arg(long, value_enum, default_value = "png")]
+    pub format: OutputFormat,
+
+    /// File to write, defaults to `call_graph.<format>`. Use `-` to write DOT, JSON,
+    /// JSON Lines or adjacency text to stdout
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<String>,
+
+    /// Directory to write every generated file into, created if missing. Relative
+    /// output paths are taken from it
+    #[arg(long, value_name = "DIR")]
+    pub output_dir: Option<PathBuf>,
+
+    /// Analyze this git revision (a commit, tag or branch) of the code instead of the
+    /// files on disk, using a temporary worktree
+    #[arg(long, value_name = "REV", conflicts_with = "watch")]
+    pub git_rev: Option<String>,
+
+    /// Keep running, and regenerate the outputs whenever a `.rs` file changes
+    #[arg(long)]
+    pub watch: bool,
+
+    /// Only report errors
+    #[arg(short, long, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Also report each traversal entry point and other details
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    /// Also draw calls into the standard library (`std::`, `core::` and `alloc::` paths
+    /// and macros like `println!`), as dashed nodes
+    #[arg(long)]
+    pub include_std: bool,
+
+    /// Only follow calls up to N levels deep from each entry point; unlimited if omitted
+    #[arg(long, value_name = "N")]
+    pub depth: Option<usize>,
+
+    /// Also write a separate `<crate>.dot` (and image) for each crate in the workspace
+    #[arg(long)]
+    pub per_crate: bool,
+
+    /// Function to start following calls from; may be repeated and may be fully qualified
+    /// (e.g. `server::handle_request`). Defaults to `main`
+    #[arg(long = "start", value_name = "NAME")]
+    pub start_functions: Vec<String>,
+
+    /// Only draw what this function reaches, starting from it alone; may be qualified
+    /// like `--start`
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["start_functions", "entries"])]
+    pub root: Option<String>,
+
+    /// How traversal entry points are chosen. `pub` starts from the whole public API
+    #[arg(long, value_enum, default_value = "start")]
+    pub entries: EntryMode,
+
+    /// Also follow calls from bench targets (`benches/` and `[[bench]]` entries) and
+    /// from `#[bench]` functions
+    #[arg(long)]
+    pub include_benches: bool,
+
+    /// Also follow calls from integration tests (`tests/` and `[[test]]` entries) and
+    /// from `#[test]` functions, which are marked as test entry points
+    #[arg(long)]
+    pub include_tests: bool,
+
+    /// Also follow calls from examples (`examples/` and `[[example]]` entries)
+    #[arg(long)]
+    pub include_examples: bool,
+
+    /// Also analyze crates depended on by path (`{ path = "../other" }`) that aren't
+    /// members of the workspace
+    #[arg(long)]
+    pub include_external_crates: bool,
+
+    /// Only follow calls into functions whose qualified name matches this regex; calls
+    /// to other functions are drawn but not followed any further
+    #[arg(long, value_name = "REGEX")]
+    pub only: Option<String>,
+
+    /// Fail if a call into a workspace crate (e.g. `my_core::parse()`) matches none of
+    /// its functions
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Workspace member to leave out of the analysis; may be repeated
+    #[arg(long = "exclude-crate", value_name = "NAME")]
+    pub exclude_crates: Vec<String>,
+
+    /// Cache what is extracted from each source file under `.ferrisfollow/`, so unchanged
+    /// files are not parsed again on the next run
+    #[arg(long)]
+    pub cache: bool,
+
+    /// Also analyze source files excluded by `.gitignore` or `.ignore`, and hidden ones
+    #[arg(long)]
+    pub no_ignore: bool,
+
+    /// Draw one node per module, with edges weighted by the number of calls between them
+    #[arg(long)]
+    pub modules: bool,
+
+    /// Leave out functions whose qualified name matches this regex, with their calls;
+    /// may be repeated
+    #[arg(long = "exclude-pattern", value_name = "REGEX")]
+    pub exclude_patterns: Vec<String>,
+
+    /// Draw one node per type in place of its methods, with edges weighted by the number
+    /// of calls between types
+    #[arg(long, conflicts_with = "modules")]
+    pub merge_impls: bool,
+
+    /// Leave out functions with this attribute, e.g. `deprecated`, with their calls; may
+    /// be repeated
+    #[arg(long = "exclude-attr", value_name = "NAME")]
+    pub exclude_attrs: Vec<String>,
+
+    /// Only draw functions with this attribute, e.g. `tracing::instrument` or just
+    /// `instrument`, and the calls between them; may be repeated
+    #[arg(long = "only-attr", value_name = "NAME")]
+    pub only_attrs: Vec<String>,
+
+    /// Merge nodes such as `foo` and `mymod::foo` whose names differ only by a module
+    /// prefix, as happens when a call's path can't be fully resolved
+    #[arg(long)]
+    pub merge_by_basename: bool,
+
+    /// Leave out functions that call nothing
+    #[arg(long)]
+    pub hide_leaves: bool,
+
+    /// Merge each run of functions that have a single caller and call a single function
+    /// into one node
+    #[arg(long)]
+    pub collapse_chains: bool,
+
+    /// Only draw the N functions with the most calls in and out, and the calls between them
+    #[arg(long, value_name = "N")]
+    pub top: Option<usize>,
+
+    /// Split graphs of more than N functions into several numbered outputs
+    /// (`call_graph_1.png`, ...) of at most N each, listed in `call_graph_index.txt`.
+    /// Calls between different outputs are left out
+    #[arg(long, value_name = "N")]
+    pub max_nodes_per_image: Option<usize>,
+
+    /// Only print the summary, without writing any files
+    #[arg(long, conflicts_with = "output")]
+    pub summary_only: bool,
+
+    /// Only write the DOT file for image formats, without running Graphviz
+    #[arg(long, visible_alias = "no-png")]
+    pub dot_only: bool,
+
+    /// Extra argument for Graphviz when rendering images, e.g. `--dot-arg=-Gsplines=ortho`;
+    /// may be repeated
+    #[arg(long = "dot-arg", value_name = "ARG", allow_hyphen_values = true)]
+    pub dot_args: Vec<String>,
+
+    /// What to show in each node of the diagram
+    #[arg(long, value_enum, default_value = "name")]
+    pub labels: LabelStyle,
+
+    /// Leave this prefix, e.g. `mycrate::internal::`, off the labels of the nodes whose
+    /// names start with it; may be repeated
+    #[arg(long = "strip-prefix", value_name = "PREFIX")]
+    pub strip_prefixes: Vec<String>,
+
+    /// Shorten node labels longer than N characters by leaving out module path segments
+    /// from the middle, e.g. `mycrate::a::…::foo`. The full name is kept in the tooltip
+    #[arg(long, value_name = "N")]
+    pub max_label_len: Option<usize>,
+
+    /// Leave out the title above the graph naming the crates, the date and the
+    /// ferrisfollow version
+    #[arg(long)]
+    pub no_title: bool,
+
+    /// Leave the sequence numbers off the edges, keeping only their colors
+    #[arg(long)]
+    pub no_edge_labels: bool,
+
+    /// Colors to grade nodes and edges with by call sequence, and to fill nodes with for
+    /// `--color-by complexity`
+    #[arg(long, value_enum, default_value = "flowbite")]
+    pub palette: Palette,
+
+    /// Background and text colors of the graph. `dark` also lightens the palette so it
+    /// stays readable
+    #[arg(long, value_enum, default_value = "light")]
+    pub theme: Theme,
+
+    /// Fill the boxes that test and example code are drawn in with colors of their own
+    #[arg(long)]
+    pub cluster_colors: bool,
+
+    /// Add to each node's label how many functions it reaches through its calls
+    #[arg(long)]
+    pub reach: bool,
+
+    /// Scale each node's size by a per-function metric
+    #[arg(long, value_enum, value_name = "METRIC")]
+    pub size_by: Option<SizeBy>,
+
+    /// Fill each node with a color scaled by a per-function metric, or with `depth`, grade
+    /// nodes and edges by call depth rather than call sequence
+    #[arg(long, value_enum, value_name = "METRIC")]
+    pub color_by: Option<ColorBy>,
+
+    /// Put functions the same number of calls away from an entry point on the same
+    /// rank, so the graph reads top to bottom by call depth
+    #[arg(long)]
+    pub rank_by_depth: bool,
+
+    /// List the functions in the summary so that each comes before everything it calls
+    #[arg(long)]
+    pub toposort: bool,
+
+    /// List in the summary how many distinct type arguments each generic function is
+    /// called with, counting calls that spell them out (`convert::<u32>()`)
+    #[arg(long)]
+    pub instantiations: bool,
+
+    /// List in the summary the method names defined on several types, where a method call
+    /// whose receiver's type isn't known may be attributed to the wrong type
+    #[arg(long)]
+    pub method_collisions: bool,
+
+    /// List the N pairs of modules with the most calls between them in the summary
+    #[arg(long, value_name = "N")]
+    pub coupling: Option<usize>,
+
+    /// Also write a CSV of how many distinct functions call each function, the most
+    /// called first
+    #[arg(long, value_name = "FILE")]
+    pub call_counts: Option<PathBuf>,
+
+    /// How many of the most complex functions to list in the summary
+    #[arg(long, value_name = "N", default_value_t = 5)]
+    pub most_complex: usize,
+
+    /// How many of the modules with the most functions to list in the summary
+    #[arg(long, value_name = "N", default_value_t = 5)]
+    pub largest_modules: usize,
+
+    /// Link SVG nodes to `<URL>/<path>#L<line>` (e.g. a GitHub blob URL) rather than
+    /// to the local source file
+    #[arg(long, value_name = "URL")]
+    pub link_base: Option<String>,
+}
@@ -0,0 +1,132 @@
+use crate::cli::{Cli, LabelStyle, OutputFormat};
+use clap::{ArgMatches, parser::ValueSource};
+use ferrisfollow::{
+    graph::{Palette, Theme},
+    workspace::EntryMode,
+};
+use serde::Deserialize;
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub const DEFAULT_CONFIG_FILE: &str = "ferrisfollow.toml";
+
+// Settings read from `ferrisfollow.toml`. The analysis settings are named after the
+// `AnalysisConfig` fields they set; the rest after the command line flags.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileConfig {
+    pub start_functions: Option<Vec<String>>,
+    pub entry_mode: Option<EntryMode>,
+    pub respect_ignore_files: Option<bool>,
+    pub include_benches: Option<bool>,
+    pub include_tests: Option<bool>,
+    pub include_examples: Option<bool>,
+    pub include_external_crates: Option<bool>,
+    pub exclude_crates: Option<Vec<String>>,
+    pub format: Option<OutputFormat>,
+    pub output: Option<String>,
+    pub output_dir: Option<PathBuf>,
+    pub per_crate: Option<bool>,
+    pub modules: Option<bool>,
+    pub labels: Option<LabelStyle>,
+    pub palette: Option<Palette>,
+    pub theme: Option<Theme>,
+    pub cluster_colors: Option<bool>,
+    pub cache: Option<bool>,
+}
+
+impl FileConfig {
+    pub fn from_path(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+        toml::from_str(&content).map_err(|e| format!("Invalid '{}': {}", path.display(), e).into())
+    }
+}
+
+// The `--config` file, or `ferrisfollow.toml` in the analyzed directory if there is one
+pub fn load(cli: &Cli) -> Result<Option<FileConfig>, Box<dyn Error>> {
+    match &cli.config {
+        Some(path) => Ok(Some(FileConfig::from_path(path)?)),
+        None => {
+            let path = cli.path.join(DEFAULT_CONFIG_FILE);
+            match cli.path.is_dir() && path.is_file() {
+                true => {
+                    log::info!("Using settings from '{}'", path.display());
+                    Ok(Some(FileConfig::from_path(&path)?))
+                }
+                false => Ok(None),
+            }
+        }
+    }
+}
+
+// Fills in every setting the command line left at its default from the file
+pub fn apply(cli: &mut Cli, matches: &ArgMatches, file: FileConfig) {
+    let unset = |id: &str| !matches!(matches.value_source(id), Some(ValueSource::CommandLine));
+
+    take(
+        &mut cli.start_functions,
+        unset("start_functions"),
+        file.start_functions,
+    );
+    take(&mut cli.entries, unset("entries"), file.entry_mode);
+    take(
+        &mut cli.no_ignore,
+        unset("no_ignore"),
+        file.respect_ignore_files.map(|respect| !respect),
+    );
+    take(
+        &mut cli.include_benches,
+        unset("include_benches"),
+        file.include_benches,
+    );
+    take(
+        &mut cli.include_tests,
+        unset("include_tests"),
+        file.include_tests,
+    );
+    take(
+        &mut cli.include_examples,
+        unset("include_examples"),
+        file.include_examples,
+    );
+    take(
+        &mut cli.include_external_crates,
+        unset("include_external_crates"),
+        file.include_external_crates,
+    );
+    take(
+        &mut cli.exclude_crates,
+        unset("exclude_crates"),
+        file.exclude_crates,
+    );
+    take(&mut cli.format, unset("format"), file.format);
+    take(&mut cli.output, unset("output"), file.output.map(Some));
+    take(
+        &mut cli.output_dir,
+        unset("output_dir"),
+        file.output_dir.map(Some),
+    );
+    take(&mut cli.per_crate, unset("per_crate"), file.per_crate);
+    take(&mut cli.modules, unset("modules"), file.modules);
+    take(&mut cli.labels, unset("labels"), file.labels);
+    take(&mut cli.palette, unset("palette"), file.palette);
+    take(&mut cli.theme, unset("theme"), file.theme);
+    take(
+        &mut cli.cluster_colors,
+        unset("cluster_colors"),
+        file.cluster_colors,
+    );
+    take(&mut cli.cache, unset("cache"), file.cache);
+}
+
+// Sets `setting` to the file's `value` when the command line left it unset
+fn take<T>(setting: &mut T, unset: bool, value: Option<T>) {
+    match (unset, value) {
+        (true, Some(value)) => *setting = value,
+        _ => (),
+    }
+}
@@ -0,0 +1,345 @@
+use crate::{utils, visitor::SourceLocation, workspace::WorkspaceAnalysis};
+use petgraph::{Graph, prelude::*};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    error::Error,
+    fs,
+    io::{self, Write},
+};
+
+// Bumped whenever a field is removed or changes meaning. Adding optional fields
+// does not change the version.
+pub const SCHEMA_VERSION: u32 = 1;
+
+// The graph as written by `--format json`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GraphExport {
+    pub schema_version: u32,
+    pub metadata: ExportMetadata,
+    pub nodes: Vec<NodeExport>,
+    pub edges: Vec<EdgeExport>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExportMetadata {
+    // Version of ferrisfollow that wrote the file
+    pub generator_version: String,
+    // Every analyzed crate, with hyphens normalized to underscores
+    pub crates: Vec<String>,
+    // The `main` functions the analysis started from
+    pub entry_points: Vec<String>,
+    // The integration test and `#[test]` functions that were followed as entry points
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub test_entry_points: Vec<String>,
+    // Likewise for bench targets and `#[bench]` functions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bench_entry_points: Vec<String>,
+}
+
+impl ExportMetadata {
+    fn new(analysis: &WorkspaceAnalysis) -> Self {
+        ExportMetadata {
+            generator_version: env!("CARGO_PKG_VERSION").to_string(),
+            crates: analysis.crate_names.clone(),
+            entry_points: analysis.get_entry_points(),
+            test_entry_points: analysis.test_entry_points.clone(),
+            bench_entry_points: analysis.bench_entry_points.clone(),
+        }
+    }
+}
+
+// A function or method, or a module when the graph was collapsed with `--modules`.
+// Everything but the name and crate is only known for functions and methods.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NodeExport {
+    // Crate-prefixed path, e.g. `mycrate::server::Server::run`
+    pub name: String,
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    // Source file as it was found from the analyzed path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    // 1-based line of the `fn` signature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    // `pub`, `pub(crate)` and so on, or `private`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<String>,
+    // Parameters and return type, e.g. `(a: u32) -> bool`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lines_of_code: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub complexity: Option<usize>,
+}
+
+// A call from one node to another, by node name
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EdgeExport {
+    pub from: String,
+    pub to: String,
+    // The call's position in traversal order, or the number of calls for a
+    // `--modules` graph
+    pub weight: usize,
+    // The lines in the caller's file where the calls are made
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lines: Vec<usize>,
+}
+
+// What the analysis knows about each function, gathered once for all the nodes and edges
+struct Details {
+    locations: HashMap<String, SourceLocation>,
+    visibilities: HashMap<String, String>,
+    signatures: HashMap<String, String>,
+    lines_of_code: HashMap<String, usize>,
+    complexity: HashMap<String, usize>,
+    call_lines: HashMap<(String, String), Vec<usize>>,
+}
+
+impl Details {
+    fn new(analysis: &WorkspaceAnalysis) -> Self {
+        Details {
+            locations: analysis.locations(),
+            visibilities: analysis.visibilities(),
+            signatures: analysis.signatures(),
+            lines_of_code: analysis.lines_of_code(),
+            complexity: analysis.complexity(),
+            call_lines: analysis.call_lines(),
+        }
+    }
+
+    fn node(&self, name: &String) -> NodeExport {
+        NodeExport {
+            name: name.clone(),
+            crate_name: utils::crate_of(name).to_string(),
+            file: self
+                .locations
+                .get(name)
+                .map(|location| location.file.clone()),
+            line: self.locations.get(name).map(|location| location.line),
+            visibility: self.visibilities.get(name).cloned(),
+            signature: self.signatures.get(name).cloned(),
+            lines_of_code: self.lines_of_code.get(name).copied(),
+            complexity: self.complexity.get(name).copied(),
+        }
+    }
+
+    fn edge(&self, graph: &Graph<String, usize, Directed>, e: EdgeIndex) -> EdgeExport {
+        let (from, to) = graph.edge_endpoints(e).unwrap();
+        EdgeExport {
+            from: graph[from].clone(),
+            to: graph[to].clone(),
+            weight: graph[e],
+            lines: self
+                .call_lines
+                .get(&(graph[from].clone(), graph[to].clone()))
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+// One line of `--format jsonl`, tagged with its `type`. The metadata line comes first,
+// with the same fields as the JSON `metadata` plus `schema_version`, then a `node`
+// line per node and an `edge` line per edge, with the fields of the JSON `nodes` and
+// `edges`.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum JsonLine<'a> {
+    Metadata {
+        schema_version: u32,
+        #[serde(flatten)]
+        metadata: &'a ExportMetadata,
+    },
+    Node(&'a NodeExport),
+    Edge(&'a EdgeExport),
+}
+
+impl GraphExport {
+    pub fn new(graph: &Graph<String, usize, Directed>, analysis: &WorkspaceAnalysis) -> Self {
+        let details = Details::new(analysis);
+        GraphExport {
+            schema_version: SCHEMA_VERSION,
+            metadata: ExportMetadata::new(analysis),
+            nodes: graph
+                .node_weights()
+                .map(|name| details.node(name))
+                .collect(),
+            edges: graph
+                .edge_indices()
+                .map(|e| details.edge(graph, e))
+                .collect(),
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+        let export: GraphExport = serde_json::from_str(json)?;
+        match export.schema_version {
+            SCHEMA_VERSION => Ok(export),
+            version => Err(format!(
+                "Unsupported schema version {} (expected {})",
+                version, SCHEMA_VERSION
+            )
+            .into()),
+        }
+    }
+
+    // The calls in edge order, as `WorkspaceAnalysis::function_calls` returns them
+    pub fn function_calls(&self) -> Vec<(String, String)> {
+        self.edges
+            .iter()
+            .map(|edge| (edge.from.clone(), edge.to.clone()))
+            .collect()
+    }
+
+    // The graph the export was written from, with the same node and edge order
+    pub fn to_graph(&self) -> Graph<String, usize, Directed> {
+        let mut graph = Graph::new();
+        let mut node_indices = HashMap::new();
+        for node in &self.nodes {
+            node_indices.insert(node.name.as_str(), graph.add_node(node.name.clone()));
+        }
+        for edge in &self.edges {
+            match (
+                node_indices.get(edge.from.as_str()),
+                node_indices.get(edge.to.as_str()),
+            ) {
+                (Some(&from), Some(&to)) => {
+                    graph.add_edge(from, to, edge.weight);
+                }
+                _ => (),
+            }
+        }
+        graph
+    }
+}
+
+// Writes the export to `filename`, or to stdout when it is `-`
+pub fn write_json_file(
+    graph: &Graph<String, usize, Directed>,
+    filename: &str,
+    analysis: &WorkspaceAnalysis,
+) -> Result<(), Box<dyn Error>> {
+    match filename {
+        "-" => write_json(graph, &mut io::stdout().lock(), analysis),
+        _ => write_json(graph, &mut fs::File::create(filename)?, analysis),
+    }
+}
+
+pub fn write_json(
+    graph: &Graph<String, usize, Directed>,
+    file: &mut impl Write,
+    analysis: &WorkspaceAnalysis,
+) -> Result<(), Box<dyn Error>> {
+    serde_json::to_writer_pretty(&mut *file, &GraphExport::new(graph, analysis))?;
+    writeln!(file)?;
+    Ok(())
+}
+
+// Writes the export as JSON Lines to `filename`, or to stdout when it is `-`
+pub fn write_jsonl_file(
+    graph: &Graph<String, usize, Directed>,
+    filename: &str,
+    analysis: &WorkspaceAnalysis,
+) -> Result<(), Box<dyn Error>> {
+    // Written out a line at a time, so whatever reads the stream sees each record whole
+    // as soon as it is written
+    match filename {
+        "-" => write_jsonl(
+            graph,
+            &mut io::LineWriter::new(io::stdout().lock()),
+            analysis,
+        ),
+        _ => write_jsonl(
+            graph,
+            &mut io::LineWriter::new(fs::File::create(filename)?),
+            analysis,
+        ),
+    }
+}
+
+// Each node and edge is written as soon as it is built, so the whole export is never
+// held in memory at once
+pub fn write_jsonl(
+    graph: &Graph<String, usize, Directed>,
+    file: &mut impl Write,
+    analysis: &WorkspaceAnalysis,
+) -> Result<(), Box<dyn Error>> {
+    let metadata = ExportMetadata::new(analysis);
+    let mut write_line = |line: JsonLine| -> Result<(), Box<dyn Error>> {
+        serde_json::to_writer(&mut *file, &line)?;
+        writeln!(file)?;
+        Ok(())
+    };
+    write_line(JsonLine::Metadata {
+        schema_version: SCHEMA_VERSION,
+        metadata: &metadata,
+    })?;
+
+    let details = Details::new(analysis);
+    for name in graph.node_weights() {
+        write_line(JsonLine::Node(&details.node(name)))?;
+    }
+    for e in graph.edge_indices() {
+        write_line(JsonLine::Edge(&details.edge(graph, e)))?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+// Writes `function,callers` CSV lines to `filename`: how many distinct functions call
+// each function, the most called first
+pub fn write_call_counts_file(
+    graph: &Graph<String, usize, Directed>,
+    filename: &str,
+) -> Result<(), Box<dyn Error>> {
+    let mut file = fs::File::create(filename)?;
+    let mut counts: Vec<(&String, usize)> = graph
+        .node_indices()
+        .map(|i| {
+            let callers: BTreeSet<NodeIndex> = graph.neighbors_directed(i, Incoming).collect();
+            (&graph[i], callers.len())
+        })
+        .collect();
+    counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+
+    writeln!(file, "function,callers")?;
+    for (name, count) in counts {
+        writeln!(file, "{},{}", name, count)?;
+    }
+    Ok(())
+}
+
+// Writes the adjacency list to `filename`, or to stdout when it is `-`
+pub fn write_adjacency_file(
+    graph: &Graph<String, usize, Directed>,
+    filename: &str,
+) -> Result<(), Box<dyn Error>> {
+    match filename {
+        "-" => write_adjacency(graph, &mut io::stdout().lock()),
+        _ => write_adjacency(graph, &mut fs::File::create(filename)?),
+    }
+}
+
+// One `caller -> callee, callee` line per function that makes calls, sorted so the
+// output diffs cleanly
+pub fn write_adjacency(
+    graph: &Graph<String, usize, Directed>,
+    file: &mut impl Write,
+) -> Result<(), Box<dyn Error>> {
+    let mut adjacency: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for edge in graph.edge_references() {
+        adjacency
+            .entry(&graph[edge.source()])
+            .or_default()
+            .insert(&graph[edge.target()]);
+    }
+
+    for (caller, callees) in adjacency {
+        let callees: Vec<&str> = callees.into_iter().collect();
+        writeln!(file, "{} -> {}", caller, callees.join(", "))?;
+    }
+    Ok(())
+}
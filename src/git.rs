@@ -0,0 +1,83 @@
+use std::{
+    env,
+    error::Error,
+    io,
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+
+// A temporary `git worktree` with some revision checked out, removed again on drop
+pub struct Worktree {
+    repository: PathBuf,
+    dir: PathBuf,
+    // Where the path the worktree was made for lies within the repository
+    relative_path: PathBuf,
+}
+
+impl Worktree {
+    // Checks out `rev` of the repository containing `path`
+    pub fn checkout(path: &Path, rev: &str) -> Result<Self, Box<dyn Error>> {
+        let path = path.canonicalize()?;
+        let search_dir = match path.is_file() {
+            true => path.parent().unwrap_or(Path::new("/")),
+            false => &path,
+        };
+        let repository = PathBuf::from(git(search_dir, &["rev-parse", "--show-toplevel"])?);
+        let relative_path = path.strip_prefix(&repository)?.to_path_buf();
+
+        let name: String = rev
+            .chars()
+            .map(|c| match c.is_ascii_alphanumeric() {
+                true => c,
+                false => '-',
+            })
+            .collect();
+        let dir = env::temp_dir().join(format!("ferrisfollow-{}-{}", name, process::id()));
+
+        let dir_str = dir.to_string_lossy();
+        git(&repository, &["worktree", "add", "--detach", &dir_str, rev])?;
+        log::info!("Checked out '{}' into '{}'", rev, dir.display());
+
+        Ok(Worktree {
+            repository,
+            dir,
+            relative_path,
+        })
+    }
+
+    // The original path's counterpart in the worktree
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(&self.relative_path)
+    }
+}
+
+impl Drop for Worktree {
+    fn drop(&mut self) {
+        let dir_str = self.dir.to_string_lossy();
+        match git(
+            &self.repository,
+            &["worktree", "remove", "--force", &dir_str],
+        ) {
+            Ok(_) => (),
+            Err(e) => log::warn!("Failed to remove worktree '{}': {}", self.dir.display(), e),
+        }
+    }
+}
+
+// Runs git in `dir`, returning its trimmed output
+fn git(dir: &Path, args: &[&str]) -> Result<String, Box<dyn Error>> {
+    let output = match Command::new("git").arg("-C").arg(dir).args(args).output() {
+        Ok(output) => output,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err("git is not installed (or not on PATH), but --git-rev needs it".into());
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git {} failed: {}", args.join(" "), error.trim()).into());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -1,23 +1,47 @@
-use crate::visitor::FunctionCallVisitor;
-use petgraph::{
-    Graph,
-    dot::{Config, Dot},
-    prelude::*,
-};
+use crate::{utils, visitor::SourceLocation, workspace::WorkspaceAnalysis};
+use petgraph::{Graph, prelude::*};
+use regex::Regex;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
     error::Error,
     fs,
-    io::Write,
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
 };
 
-pub fn create_call_graph(visitor: &FunctionCallVisitor) -> Graph<String, usize, Directed> {
+pub fn create_combined_graph(analysis: &WorkspaceAnalysis) -> Graph<String, usize, Directed> {
+    graph_from_calls(&analysis.function_calls())
+}
+
+// Graph of a single crate's calls. Calls into other crates point at a stub node
+// named after the callee's crate rather than at the function itself.
+pub fn create_crate_graph(
+    analysis: &WorkspaceAnalysis,
+    crate_name: &str,
+) -> Graph<String, usize, Directed> {
+    let function_calls: Vec<(String, String)> = analysis
+        .function_calls()
+        .into_iter()
+        .filter(|(caller, _)| utils::crate_of(caller) == crate_name)
+        .map(
+            |(caller, callee)| match utils::crate_of(&callee) == crate_name {
+                true => (caller, callee),
+                false => (caller, utils::crate_of(&callee).to_string()),
+            },
+        )
+        .collect();
+
+    graph_from_calls(&function_calls)
+}
+
+fn graph_from_calls(function_calls: &[(String, String)]) -> Graph<String, usize, Directed> {
     let mut graph = Graph::new();
     let mut node_indices = HashMap::new();
 
-    // Create nodes
+    // Create nodes. Names are crate-prefixed, so same-named functions in
+    // different crates stay distinct
     let mut seen_functions = HashSet::new();
-    for (caller, callee) in &visitor.function_calls {
+    for (caller, callee) in function_calls {
         seen_functions.insert(caller.clone());
         seen_functions.insert(callee.clone());
     }
@@ -32,7 +56,7 @@ pub fn create_call_graph(visitor: &FunctionCallVisitor) -> Graph<String, usize,
     }
 
     // Create edges with sequence numbers
-    for (sequence, (caller, callee)) in visitor.function_calls.iter().enumerate() {
+    for (sequence, (caller, callee)) in function_calls.iter().enumerate() {
         if let (Some(&caller_idx), Some(&callee_idx)) =
             (node_indices.get(caller), node_indices.get(callee))
         {
@@ -43,98 +67,955 @@ pub fn create_call_graph(visitor: &FunctionCallVisitor) -> Graph<String, usize,
     graph
 }
 
+// Flowbite color palette
+const FLOWBITE: [&str; 27] = [
+    // Blues
+    "#1e40af", // Blue-800
+    "#1d4ed8", // Blue-700
+    "#2563eb", // Blue-600
+    "#3b82f6", // Blue-500
+    "#60a5fa", // Blue-400
+    // Purples
+    "#6d28d9", // Purple-700
+    "#7c3aed", // Purple-600
+    "#8b5cf6", // Purple-500
+    "#a78bfa", // Purple-400
+    // Pinks
+    "#be185d", // Pink-700
+    "#db2777", // Pink-600
+    "#ec4899", // Pink-500
+    "#f472b6", // Pink-400
+    // Greens
+    "#166534", // Green-700
+    "#16a34a", // Green-600
+    "#22c55e", // Green-500
+    "#4ade80", // Green-400
+    "#86efac", // Green-300
+    // Yellows
+    "#facc15", // Yellow-400
+    "#fbbf24", // Yellow-400
+    "#f59e0b", // Yellow-500
+    // Oranges
+    "#ea580c", // Orange-600
+    "#f97316", // Orange-500
+    "#fb923c", // Orange-400
+    // Reds
+    "#dc2626", // Red-600
+    "#b91c1c", // Red-700
+    "#991b1b", // Red-800
+];
+
+// Viridis, which stays distinguishable with the common kinds of color blindness
+const VIRIDIS: [&str; 16] = [
+    "#440154", "#481a6c", "#472f7d", "#414487", "#39568c", "#31688e", "#2a788e", "#23888e",
+    "#1f988b", "#22a884", "#35b779", "#54c568", "#7ad151", "#a5db36", "#d2e21b", "#fde725",
+];
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Palette {
+    /// Blues through purples, pinks, greens, yellows and oranges to reds
+    #[default]
+    Flowbite,
+    /// The colorblind-safe viridis ramp from dark purple to yellow
+    Viridis,
+}
+
+impl Palette {
+    // The gradient edges and nodes are colored along, in call sequence order. On a
+    // dark background the darkest shades are swapped for lighter ones.
+    pub fn colors(self, theme: Theme) -> &'static [&'static str] {
+        match (self, theme) {
+            (Palette::Flowbite, Theme::Light) => &FLOWBITE,
+            (Palette::Flowbite, Theme::Dark) => &FLOWBITE_DARK,
+            (Palette::Viridis, Theme::Light) => &VIRIDIS,
+            (Palette::Viridis, Theme::Dark) => &VIRIDIS[5..],
+        }
+    }
+
+    // Low to high, for filling nodes by a metric like complexity
+    pub fn heat(self) -> &'static [&'static str] {
+        match self {
+            Palette::Flowbite => &FLOWBITE_HEAT,
+            Palette::Viridis => &VIRIDIS,
+        }
+    }
+}
+
+// Flowbite's greens through yellows and oranges to reds, for filling nodes by a metric
+const FLOWBITE_HEAT: [&str; 8] = [
+    "#4ade80", // Green-400
+    "#86efac", // Green-300
+    "#facc15", // Yellow-400
+    "#f59e0b", // Yellow-500
+    "#fb923c", // Orange-400
+    "#f97316", // Orange-500
+    "#dc2626", // Red-600
+    "#b91c1c", // Red-700
+];
+
+// Flowbite's lighter shades, for dark backgrounds
+const FLOWBITE_DARK: [&str; 21] = [
+    "#93c5fd", // Blue-300
+    "#60a5fa", // Blue-400
+    "#3b82f6", // Blue-500
+    "#c4b5fd", // Purple-300
+    "#a78bfa", // Purple-400
+    "#8b5cf6", // Purple-500
+    "#f9a8d4", // Pink-300
+    "#f472b6", // Pink-400
+    "#ec4899", // Pink-500
+    "#86efac", // Green-300
+    "#4ade80", // Green-400
+    "#22c55e", // Green-500
+    "#fde047", // Yellow-300
+    "#facc15", // Yellow-400
+    "#fbbf24", // Yellow-400
+    "#fdba74", // Orange-300
+    "#fb923c", // Orange-400
+    "#f97316", // Orange-500
+    "#fca5a5", // Red-300
+    "#f87171", // Red-400
+    "#ef4444", // Red-500
+];
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// Dark text and lines on Graphviz's default white background
+    #[default]
+    Light,
+    /// Light text and lines on a dark slate background
+    Dark,
+}
+
+impl Theme {
+    // The graph's `bgcolor`, or `None` to leave Graphviz's white
+    pub fn background(self) -> Option<&'static str> {
+        match self {
+            Theme::Light => None,
+            Theme::Dark => Some("#0f172a"),
+        }
+    }
+
+    // Text, and the outline of nodes no call reaches
+    pub fn foreground(self) -> &'static str {
+        match self {
+            Theme::Light => "black",
+            Theme::Dark => "#e2e8f0",
+        }
+    }
+}
+
+// Only the `limit` nodes with the most incoming plus outgoing edges, ties broken by
+// name, and the edges between them
+pub fn keep_most_connected(
+    graph: &Graph<String, usize, Directed>,
+    limit: usize,
+) -> Graph<String, usize, Directed> {
+    let mut ranked: Vec<NodeIndex> = graph.node_indices().collect();
+    ranked.sort_by_key(|&i| {
+        let degree = graph.edges_directed(i, Incoming).count() + graph.edges(i).count();
+        (std::cmp::Reverse(degree), graph[i].clone())
+    });
+    let kept: HashSet<NodeIndex> = ranked.into_iter().take(limit).collect();
+
+    graph.filter_map(
+        |i, name| kept.contains(&i).then(|| name.clone()),
+        |_, &weight| Some(weight),
+    )
+}
+
+// How many calls each function is from the nearest of the entry points traversal started
+// from, or from the nearest function that nothing calls where none of them is in the
+// graph. Functions reached from neither count from the first of them.
+pub fn call_depths(
+    graph: &Graph<String, usize, Directed>,
+    entries: &[String],
+) -> HashMap<String, usize> {
+    let mut depths: HashMap<NodeIndex, usize> = HashMap::new();
+    let roots: Vec<NodeIndex> = graph
+        .node_indices()
+        .filter(|&i| {
+            chain_members(&graph[i])
+                .iter()
+                .any(|member| entries.iter().any(|entry| entry == member))
+        })
+        .collect();
+    let roots = match roots.is_empty() {
+        true => graph
+            .node_indices()
+            .filter(|&i| graph.neighbors_directed(i, Incoming).next().is_none())
+            .collect(),
+        false => roots,
+    };
+    spread_depths(graph, &mut depths, roots);
+    for i in graph.node_indices() {
+        if !depths.contains_key(&i) {
+            spread_depths(graph, &mut depths, vec![i]);
+        }
+    }
+
+    depths
+        .into_iter()
+        .map(|(i, depth)| (graph[i].clone(), depth))
+        .collect()
+}
+
+// Breadth-first from all of `starts` at once, so each node gets the depth from the
+// nearest of them
+fn spread_depths(
+    graph: &Graph<String, usize, Directed>,
+    depths: &mut HashMap<NodeIndex, usize>,
+    starts: Vec<NodeIndex>,
+) {
+    let mut queue = VecDeque::new();
+    for start in starts {
+        depths.insert(start, 0);
+        queue.push_back(start);
+    }
+    while let Some(node) = queue.pop_front() {
+        for next in graph.neighbors(node) {
+            if !depths.contains_key(&next) {
+                depths.insert(next, depths[&node] + 1);
+                queue.push_back(next);
+            }
+        }
+    }
+}
+
+// Splits the graph into pieces of at most `max_nodes` nodes, each keeping the edges
+// among its own nodes. Connected components are kept whole where they fit, with small
+// ones packed together; larger ones are cut into breadth-first neighborhoods.
+pub fn partition(
+    graph: &Graph<String, usize, Directed>,
+    max_nodes: usize,
+) -> Vec<Graph<String, usize, Directed>> {
+    let max_nodes = max_nodes.max(1);
+
+    // Connected components, ignoring edge direction, each in breadth-first order
+    let mut visited = HashSet::new();
+    let mut pieces: Vec<Vec<NodeIndex>> = Vec::new();
+    for start in graph.node_indices() {
+        if !visited.insert(start) {
+            continue;
+        }
+        let mut component = Vec::new();
+        let mut queue = std::collections::VecDeque::from([start]);
+        while let Some(node) = queue.pop_front() {
+            component.push(node);
+            for neighbor in graph.neighbors_undirected(node) {
+                if visited.insert(neighbor) {
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        pieces.extend(component.chunks(max_nodes).map(|chunk| chunk.to_vec()));
+    }
+
+    let mut chunks: Vec<Vec<NodeIndex>> = Vec::new();
+    for piece in pieces {
+        match chunks
+            .iter_mut()
+            .find(|chunk| chunk.len() + piece.len() <= max_nodes)
+        {
+            Some(chunk) => chunk.extend(piece),
+            None => chunks.push(piece),
+        }
+    }
+
+    chunks
+        .into_iter()
+        .map(|chunk| {
+            let kept: HashSet<NodeIndex> = chunk.into_iter().collect();
+            graph.filter_map(
+                |i, name| kept.contains(&i).then(|| name.clone()),
+                |_, &weight| Some(weight),
+            )
+        })
+        .collect()
+}
+
+// Every node ordered so that callers come before their callees, or, when calls form
+// a cycle (recursion included), the name of a node on it
+pub fn topological_order(graph: &Graph<String, usize, Directed>) -> Result<Vec<String>, String> {
+    match petgraph::algo::toposort(graph, None) {
+        Ok(order) => Ok(order.into_iter().map(|i| graph[i].clone()).collect()),
+        Err(cycle) => Err(graph[cycle.node_id()].clone()),
+    }
+}
+
+// How many functions the largest strongly connected component has, i.e. the biggest
+// group of functions that can all reach each other through calls. Without any cycles
+// that is a single function.
+pub fn largest_component_size(graph: &Graph<String, usize, Directed>) -> usize {
+    petgraph::algo::tarjan_scc(graph)
+        .iter()
+        .map(|component| component.len())
+        .max()
+        .unwrap_or(0)
+}
+
+// Merges nodes like `foo` and `mymod::foo` that are probably the same function with a
+// path that was resolved differently. A name is merged into the longest name that ends
+// with it; names that could belong to unrelated functions are left alone.
+pub fn merge_by_basename(graph: &Graph<String, usize, Directed>) -> Graph<String, usize, Directed> {
+    let mut by_basename: HashMap<&str, Vec<&String>> = HashMap::new();
+    for name in graph.node_weights() {
+        let basename = name.rsplit("::").next().unwrap_or(name);
+        by_basename.entry(basename).or_default().push(name);
+    }
+
+    // Names carry a crate prefix, so compare what follows it, and only within a crate.
+    // Two crates' functions of the same name are different functions.
+    let is_suffix_of = |short: &str, long: &str| {
+        utils::crate_of(short) == utils::crate_of(long)
+            && without_crate(long).ends_with(&format!("::{}", without_crate(short)))
+    };
+
+    let mut renames = HashMap::new();
+    for names in by_basename.values() {
+        for &short in names {
+            let longer: Vec<&String> = names
+                .iter()
+                .copied()
+                .filter(|long| is_suffix_of(short, long))
+                .collect();
+            // Merge into the longest name, as long as the others are all suffixes of it
+            match longer.iter().max_by_key(|long| long.len()) {
+                Some(&longest)
+                    if longer
+                        .iter()
+                        .all(|&long| long == longest || is_suffix_of(long, longest)) =>
+                {
+                    renames.insert(short.clone(), longest.clone());
+                }
+                _ => (),
+            }
+        }
+    }
+
+    rename_nodes(graph, &renames)
+}
+
+// One node per type in place of its methods, named as in `types`, with an edge wherever
+// one type's methods call another's, weighted by how many such calls there are
+pub fn merge_impls(
+    graph: &Graph<String, usize, Directed>,
+    types: &HashMap<String, String>,
+) -> Graph<String, usize, Directed> {
+    let renamed = rename_nodes(graph, types);
+
+    let mut merged = renamed.filter_map(|_, name| Some(name.clone()), |_, _| None::<usize>);
+    for e in renamed.edge_indices() {
+        let (from, to) = renamed.edge_endpoints(e).unwrap();
+        if from == to {
+            continue;
+        }
+        match merged.find_edge(from, to) {
+            Some(edge) => merged[edge] += 1,
+            None => {
+                merged.add_edge(from, to, 1);
+            }
+        }
+    }
+    merged
+}
+
+fn without_crate(name: &str) -> &str {
+    name.split_once("::").map_or(name, |(_, rest)| rest)
+}
+
+// The graph with nodes renamed, where nodes renamed alike become one node with all
+// of their edges
+fn rename_nodes(
+    graph: &Graph<String, usize, Directed>,
+    renames: &HashMap<String, String>,
+) -> Graph<String, usize, Directed> {
+    let mut renamed = Graph::new();
+    let mut node_indices: HashMap<&String, NodeIndex> = HashMap::new();
+    let mut new_index = Vec::with_capacity(graph.node_count());
+    for name in graph.node_weights() {
+        let name = renames.get(name).unwrap_or(name);
+        let index = *node_indices
+            .entry(name)
+            .or_insert_with(|| renamed.add_node(name.clone()));
+        new_index.push(index);
+    }
+
+    for e in graph.edge_indices() {
+        let (from, to) = graph.edge_endpoints(e).unwrap();
+        renamed.add_edge(new_index[from.index()], new_index[to.index()], graph[e]);
+    }
+
+    renamed
+}
+
+// Only the nodes `keep` accepts by name, with the edges between them
+pub fn filter_nodes(
+    graph: &Graph<String, usize, Directed>,
+    keep: impl Fn(&str) -> bool,
+) -> Graph<String, usize, Directed> {
+    graph.filter_map(
+        |_, name| keep(name).then(|| name.clone()),
+        |_, &weight| Some(weight),
+    )
+}
+
+// Only `root` and the nodes it reaches, with the edges between them
+pub fn reachable_subgraph(
+    graph: &Graph<String, usize, Directed>,
+    root: &str,
+) -> Graph<String, usize, Directed> {
+    let mut reached = HashSet::new();
+    match graph.node_indices().find(|&i| graph[i] == root) {
+        Some(start) => {
+            let mut dfs = Dfs::new(graph, start);
+            while let Some(i) = dfs.next(graph) {
+                reached.insert(i);
+            }
+        }
+        None => (),
+    }
+
+    graph.filter_map(
+        |i, name| reached.contains(&i).then(|| name.clone()),
+        |_, &weight| Some(weight),
+    )
+}
+
+// Without the nodes any of `patterns` match, and their edges
+pub fn remove_matching(
+    graph: &Graph<String, usize, Directed>,
+    patterns: &[Regex],
+) -> Graph<String, usize, Directed> {
+    graph.filter_map(
+        |_, name| (!patterns.iter().any(|pattern| pattern.is_match(name))).then(|| name.clone()),
+        |_, &weight| Some(weight),
+    )
+}
+
+// Drops every node that calls nothing, along with the calls into it
+pub fn hide_leaves(graph: &Graph<String, usize, Directed>) -> Graph<String, usize, Directed> {
+    graph.filter_map(
+        |i, name| graph.edges(i).next().is_some().then(|| name.clone()),
+        |_, &weight| Some(weight),
+    )
+}
+
+const CHAIN_SEPARATOR: &str = " → ";
+
+// The functions `collapse_chains` merged into a node, in call order, or just the node's
+// own name for any other node
+pub fn chain_members(name: &str) -> Vec<&str> {
+    name.split(CHAIN_SEPARATOR).collect()
+}
+
+// Merges each run of functions that are called from just one function and call just one
+// function in turn, like `B` and `C` in `A -> B -> C -> D`, into one node named
+// `B → C`. Calls into and out of the chain are kept; the calls within it are dropped.
+pub fn collapse_chains(graph: &Graph<String, usize, Directed>) -> Graph<String, usize, Directed> {
+    let node_count = graph.node_count();
+    // Calling the same function twice is still calling one function
+    let distinct = |i: NodeIndex, direction: Direction| {
+        graph
+            .neighbors_directed(i, direction)
+            .collect::<HashSet<_>>()
+            .len()
+    };
+    let middle: Vec<bool> = graph
+        .node_indices()
+        .map(|i| distinct(i, Outgoing) == 1 && distinct(i, Incoming) == 1)
+        .collect();
+
+    // `next[a] == Some(b)` when `a -> b` is a link of a chain
+    let mut next: Vec<Option<usize>> = vec![None; node_count];
+    let mut has_previous = vec![false; node_count];
+    for e in graph.edge_indices() {
+        let (from, to) = graph.edge_endpoints(e).unwrap();
+        let (from, to) = (from.index(), to.index());
+        if from != to && middle[from] && middle[to] {
+            next[from] = Some(to);
+            has_previous[to] = true;
+        }
+    }
+
+    // Walk each chain from its first node; whatever is left over are chains that loop
+    // back on themselves, which start from their lowest node
+    let mut chain_of: Vec<Option<usize>> = vec![None; node_count];
+    let mut chains: Vec<Vec<usize>> = Vec::new();
+    let starts = (0..node_count)
+        .filter(|&i| !has_previous[i])
+        .chain(0..node_count);
+    for start in starts {
+        if chain_of[start].is_some() {
+            continue;
+        }
+        let mut chain = Vec::new();
+        let mut current = Some(start);
+        while let Some(i) = current.filter(|&i| chain_of[i].is_none()) {
+            chain_of[i] = Some(chains.len());
+            chain.push(i);
+            current = next[i];
+        }
+        chains.push(chain);
+    }
+
+    // Keep nodes in their original order by sorting chains by their lowest node
+    let mut order: Vec<usize> = (0..chains.len()).collect();
+    order.sort_by_key(|&c| chains[c].iter().min().copied());
+
+    let mut collapsed = Graph::new();
+    let mut new_index = vec![NodeIndex::new(0); chains.len()];
+    for c in order {
+        let name = chains[c]
+            .iter()
+            .map(|&i| graph[NodeIndex::new(i)].as_str())
+            .collect::<Vec<_>>()
+            .join(CHAIN_SEPARATOR);
+        new_index[c] = collapsed.add_node(name);
+    }
+
+    for e in graph.edge_indices() {
+        let (from, to) = graph.edge_endpoints(e).unwrap();
+        let from_chain = chain_of[from.index()].unwrap();
+        let to_chain = chain_of[to.index()].unwrap();
+        if from_chain != to_chain || from == to {
+            collapsed.add_edge(new_index[from_chain], new_index[to_chain], graph[e]);
+        }
+    }
+
+    collapsed
+}
+
+#[derive(Clone, Default)]
+pub struct DotOptions {
+    // Display labels by node name; nodes without one are labelled with their name
+    pub node_labels: HashMap<String, String>,
+    // Edge weights are call counts of merged edges rather than sequence numbers, so
+    // edges are colored by their order in the graph and labelled with the count
+    pub aggregate: bool,
+    // Extra DOT attributes by node name, e.g. `shape=diamond`. They come after the
+    // default attributes, so they take precedence over them.
+    pub node_attributes: HashMap<String, Vec<String>>,
+    // Extra DOT attributes by the names of an edge's endpoints, e.g. `style=dashed`
+    pub edge_attributes: HashMap<(String, String), Vec<String>>,
+    pub palette: Palette,
+    pub theme: Theme,
+    // Leave the sequence numbers (or call counts) off the edges
+    pub hide_edge_labels: bool,
+    // Drawn above the graph
+    pub title: Option<String>,
+    // Call depth by node name, to color by in place of the call sequence. Each edge
+    // takes its callee's depth.
+    pub depths: Option<HashMap<String, usize>>,
+    // The cluster each node is drawn in by node name, and the background of each
+    // cluster. Nodes without one are drawn outside any cluster.
+    pub clusters: HashMap<String, String>,
+    pub cluster_colors: HashMap<String, String>,
+    // Call depth by node name, to put each depth's nodes on the same rank so the graph
+    // reads top to bottom by depth. Nodes without one are ranked freely.
+    pub ranks: Option<HashMap<String, usize>>,
+}
+
+impl DotOptions {
+    pub fn add_node_attribute(&mut self, node: &str, attribute: String) {
+        self.node_attributes
+            .entry(node.to_string())
+            .or_default()
+            .push(attribute);
+    }
+
+    pub fn add_edge_attribute(&mut self, from: &str, to: &str, attribute: String) {
+        self.edge_attributes
+            .entry((from.to_string(), to.to_string()))
+            .or_default()
+            .push(attribute);
+    }
+
+    // The options with each node of `graph` that collapses a chain labelled and styled
+    // after the functions it merges: their labels joined like its name, their attributes,
+    // and their tooltips as one. Calls into and out of a chain keep their attributes.
+    pub fn with_chains(&self, graph: &Graph<String, usize, Directed>) -> DotOptions {
+        let mut options = self.clone();
+        for name in graph.node_weights() {
+            let members = chain_members(name);
+            if members.len() < 2 {
+                continue;
+            }
+            let label = members
+                .iter()
+                .map(|&member| self.node_labels.get(member).map_or(member, |l| l.as_str()))
+                .collect::<Vec<_>>()
+                .join(CHAIN_SEPARATOR);
+            options.node_labels.insert(name.clone(), label);
+
+            let mut tooltips = Vec::new();
+            let mut attributes: Vec<String> = Vec::new();
+            for attribute in members
+                .iter()
+                .flat_map(|&member| self.node_attributes.get(member))
+                .flatten()
+            {
+                match attribute
+                    .strip_prefix("tooltip=\"")
+                    .and_then(|tooltip| tooltip.strip_suffix('"'))
+                {
+                    Some(tooltip) => tooltips.push(tooltip),
+                    None if !attributes.contains(attribute) => attributes.push(attribute.clone()),
+                    None => (),
+                }
+            }
+            if !tooltips.is_empty() {
+                attributes.push(format!("tooltip=\"{}\"", tooltips.join("\n")));
+            }
+            options.node_attributes.insert(name.clone(), attributes);
+        }
+
+        for edge in graph.edge_references() {
+            let (from, to) = (&graph[edge.source()], &graph[edge.target()]);
+            let caller = chain_members(from).last().copied().unwrap_or(from);
+            let callee = chain_members(to)[0];
+            match self
+                .edge_attributes
+                .get(&(caller.to_string(), callee.to_string()))
+            {
+                Some(attributes) => {
+                    let key = (from.clone(), to.clone());
+                    options.edge_attributes.insert(key, attributes.clone());
+                }
+                None => (),
+            }
+        }
+        options
+    }
+}
+
+// Width/height attributes that grow linearly with `metric`, so the node with the
+// largest value gets the biggest box
+pub fn size_attributes(metric: &HashMap<String, usize>) -> HashMap<String, String> {
+    let max = metric.values().copied().max().unwrap_or(1).max(1) as f32;
+
+    metric
+        .iter()
+        .map(|(name, &value)| {
+            let scale = value as f32 / max;
+            let attribute = format!(
+                "width={:.2}, height={:.2}",
+                0.75 + 2.25 * scale,
+                0.5 + 1.0 * scale
+            );
+            (name.clone(), attribute)
+        })
+        .collect()
+}
+
+// Fill colors running along the palette's heat scale, from its first color for the
+// lowest value of `metric` to its last for the highest
+pub fn heat_attributes(
+    metric: &HashMap<String, usize>,
+    palette: Palette,
+) -> HashMap<String, String> {
+    let heat = palette.heat();
+    let min = metric.values().copied().min().unwrap_or(0);
+    let max = metric.values().copied().max().unwrap_or(0);
+    let range = (max - min).max(1) as f32;
+
+    metric
+        .iter()
+        .map(|(name, &value)| {
+            let index = ((value - min) as f32 * (heat.len() - 1) as f32 / range) as usize;
+            let attribute = format!("style=filled, fillcolor=\"{}\"", heat[index]);
+            (name.clone(), attribute)
+        })
+        .collect()
+}
+
+// `URL` attributes pointing at where each function is defined. With a `link_base`
+// the link is `<link_base>/<path relative to root>#L<line>`, otherwise it is a
+// `file://` URL of the source file.
+pub fn source_link_attributes(
+    locations: &HashMap<String, SourceLocation>,
+    roots: &[PathBuf],
+    link_base: Option<&str>,
+) -> HashMap<String, String> {
+    locations
+        .iter()
+        .map(|(name, location)| {
+            let file = Path::new(&location.file);
+            let url = match link_base {
+                Some(base) => {
+                    // Relative to whichever analyzed root the file is under, the most
+                    // specific one if they nest
+                    let relative = roots
+                        .iter()
+                        .filter_map(|root| file.strip_prefix(root).ok())
+                        .min_by_key(|relative| relative.components().count())
+                        .unwrap_or(file)
+                        .components()
+                        .filter(|c| !matches!(c, Component::CurDir))
+                        .map(|c| c.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    format!("{}/{}", base.trim_end_matches('/'), relative)
+                }
+                None => {
+                    let absolute = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+                    format!("file://{}", absolute.display())
+                }
+            };
+            let attribute = format!("URL=\"{}#L{}\"", url.replace('"', "%22"), location.line);
+            (name.clone(), attribute)
+        })
+        .collect()
+}
+
+// Writes the DOT to `filename`, or to stdout when it is `-`
 pub fn write_dot_file(
     graph: &Graph<String, usize, Directed>,
     filename: &str,
+    options: &DotOptions,
 ) -> Result<(), Box<dyn Error>> {
-    let mut file = fs::File::create(filename)?;
-
-    // Flowbite color palette
-    let colors = [
-        // Blues
-        "#1e40af", // Blue-800
-        "#1d4ed8", // Blue-700
-        "#2563eb", // Blue-600
-        "#3b82f6", // Blue-500
-        "#60a5fa", // Blue-400
-        // Purples
-        "#6d28d9", // Purple-700
-        "#7c3aed", // Purple-600
-        "#8b5cf6", // Purple-500
-        "#a78bfa", // Purple-400
-        // Pinks
-        "#be185d", // Pink-700
-        "#db2777", // Pink-600
-        "#ec4899", // Pink-500
-        "#f472b6", // Pink-400
-        // Greens
-        "#166534", // Green-700
-        "#16a34a", // Green-600
-        "#22c55e", // Green-500
-        "#4ade80", // Green-400
-        "#86efac", // Green-300
-        // Yellows
-        "#facc15", // Yellow-400
-        "#fbbf24", // Yellow-400
-        "#f59e0b", // Yellow-500
-        // Oranges
-        "#ea580c", // Orange-600
-        "#f97316", // Orange-500
-        "#fb923c", // Orange-400
-        // Reds
-        "#dc2626", // Red-600
-        "#b91c1c", // Red-700
-        "#991b1b", // Red-800
-    ];
+    match filename {
+        "-" => write_dot(graph, &mut io::stdout().lock(), options),
+        _ => write_dot(graph, &mut fs::File::create(filename)?, options),
+    }
+}
 
-    writeln!(file, "digraph {{")?;
-    writeln!(file, "    node [shape=box];\n")?;
+pub fn write_dot(
+    graph: &Graph<String, usize, Directed>,
+    file: &mut impl Write,
+    options: &DotOptions,
+) -> Result<(), Box<dyn Error>> {
+    let (node_colors, edge_colors) = default_colors(graph, options);
+    let foreground = options.theme.foreground().to_string();
+    let joined = |default: String, attributes: Option<&Vec<String>>| {
+        std::iter::once(default)
+            .chain(attributes.into_iter().flatten().cloned())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    write_dot_with(
+        graph,
+        file,
+        options,
+        |node| {
+            let color = node_colors.get(node).unwrap_or(&foreground);
+            joined(
+                format!("color=\"{}\", penwidth=2.0", color),
+                options.node_attributes.get(node),
+            )
+        },
+        |from, to, weight| {
+            let key = (from.to_string(), to.to_string());
+            joined(
+                edge_colors[&(from.to_string(), to.to_string(), weight)].clone(),
+                options.edge_attributes.get(&key),
+            )
+        },
+    )
+}
+
+// An edge by the names of its endpoints and its weight
+type EdgeKey = (String, String, usize);
 
-    let num_calls = graph.edge_count();
+// The coloring `write_dot` gives the graph: each edge is colored by its place in the
+// palette's gradient, and each node takes the color of its last incoming edge
+fn default_colors(
+    graph: &Graph<String, usize, Directed>,
+    options: &DotOptions,
+) -> (HashMap<String, String>, HashMap<EdgeKey, String>) {
+    let colors = options.palette.colors(options.theme);
 
-    // Create a map to store the last incoming edge color for each node
-    let mut node_colors: HashMap<NodeIndex, &str> = HashMap::new();
+    // Not the edge count: sequence numbers have gaps once nodes are filtered out
+    let last_sequence = graph
+        .edge_indices()
+        .map(|e| edge_sequence(graph, e, options))
+        .max()
+        .unwrap_or(0);
+    let max_weight = graph.edge_weights().copied().max().unwrap_or(1);
 
-    // First pass: determine node colors based on incoming edges
+    let mut node_colors = HashMap::new();
+    let mut edge_colors = HashMap::new();
     for e in graph.edge_indices() {
-        let (_, to) = graph.edge_endpoints(e).unwrap();
-        let sequence = graph.edge_weight(e).unwrap();
-        let color_index =
-            ((sequence - 1) as f32 * (colors.len() - 1) as f32 / (num_calls - 1) as f32) as usize;
-        node_colors.insert(to, colors[color_index]);
+        let (from, to) = graph.edge_endpoints(e).unwrap();
+        let sequence = edge_sequence(graph, e, options);
+        let weight = graph[e];
+        let color_index = ((sequence - 1) as f32 * (colors.len() - 1) as f32
+            / (last_sequence - 1) as f32) as usize;
+        let color = colors[color_index];
+        let fontcolor = match options.hide_edge_labels {
+            true => String::new(),
+            false => format!(", fontcolor=\"{}\"", color),
+        };
+        node_colors.insert(graph[to].clone(), color.to_string());
+        edge_colors.insert(
+            (graph[from].clone(), graph[to].clone(), weight),
+            format!(
+                "color=\"{}\"{}, penwidth={:.1}",
+                color,
+                fontcolor,
+                edge_penwidth(weight, max_weight, options)
+            ),
+        );
     }
+    (node_colors, edge_colors)
+}
+
+// Writes the DOT to `filename` like `write_dot_file`, but takes the attributes of each
+// node and edge, coloring included, from `node_attributes` (by node name) and
+// `edge_attributes` (by the names of its endpoints and its weight) rather than from
+// `options`
+pub fn write_dot_file_with(
+    graph: &Graph<String, usize, Directed>,
+    filename: &str,
+    options: &DotOptions,
+    node_attributes: impl Fn(&str) -> String,
+    edge_attributes: impl Fn(&str, &str, usize) -> String,
+) -> Result<(), Box<dyn Error>> {
+    match filename {
+        "-" => write_dot_with(
+            graph,
+            &mut io::stdout().lock(),
+            options,
+            node_attributes,
+            edge_attributes,
+        ),
+        _ => write_dot_with(
+            graph,
+            &mut fs::File::create(filename)?,
+            options,
+            node_attributes,
+            edge_attributes,
+        ),
+    }
+}
+
+// The attributes returned for a node or edge, e.g. `color=red, penwidth=3`, are all it
+// gets besides its label, so they replace `write_dot`'s coloring. An empty string
+// leaves the node or edge to Graphviz's defaults.
+pub fn write_dot_with(
+    graph: &Graph<String, usize, Directed>,
+    file: &mut impl Write,
+    options: &DotOptions,
+    node_attributes: impl Fn(&str) -> String,
+    edge_attributes: impl Fn(&str, &str, usize) -> String,
+) -> Result<(), Box<dyn Error>> {
+    let foreground = options.theme.foreground();
+
+    writeln!(file, "digraph {{")?;
+    match options.theme.background() {
+        Some(background) => writeln!(
+            file,
+            "    bgcolor=\"{}\";\n    fontcolor=\"{}\";",
+            background, foreground
+        )?,
+        None => (),
+    }
+    match &options.title {
+        Some(title) => writeln!(
+            file,
+            "    label=\"{}\";\n    labelloc=t;",
+            title.replace("\"", "")
+        )?,
+        None => (),
+    }
+    match options.theme {
+        Theme::Light => writeln!(file, "    node [shape=box];\n")?,
+        Theme::Dark => writeln!(
+            file,
+            "    node [shape=box, fontcolor=\"{}\"];\n",
+            foreground
+        )?,
+    }
+
+    // Nodes are identified by name rather than by index, so each function keeps its id
+    // from one run to the next. A repeated name gets a numbered suffix.
+    let mut name_counts: HashMap<&str, usize> = HashMap::new();
+    let ids: HashMap<NodeIndex, String> = graph
+        .node_indices()
+        .map(|i| {
+            let count = name_counts.entry(&graph[i]).or_default();
+            *count += 1;
+            let name = graph[i].replace("\"", "");
+            let id = match *count {
+                1 => format!("\"{}\"", name),
+                n => format!("\"{}#{}\"", name, n),
+            };
+            (i, id)
+        })
+        .collect();
 
-    // Add nodes with colors
+    // Add nodes, each cluster's together
+    let mut clustered: BTreeMap<&String, Vec<String>> = BTreeMap::new();
     for i in graph.node_indices() {
-        let color = node_colors.get(&i).unwrap_or(&"black");
+        let label = options.node_labels.get(&graph[i]).unwrap_or(&graph[i]);
+        let attributes = match node_attributes(&graph[i]) {
+            attributes if attributes.is_empty() => attributes,
+            attributes => format!(", {}", attributes),
+        };
+        let line = format!(
+            "{} [label=\"{}\"{}];",
+            ids[&i],
+            label.replace("\"", ""),
+            attributes
+        );
+        match options.clusters.get(&graph[i]) {
+            Some(cluster) => clustered.entry(cluster).or_default().push(line),
+            None => writeln!(file, "    {}", line)?,
+        }
+    }
+    for (cluster, lines) in clustered {
         writeln!(
             file,
-            "    {} [label=\"{}\", color=\"{}\", penwidth=2.0];",
-            i.index(),
-            graph[i].replace("\"", ""),
-            color
+            "    subgraph \"cluster_{}\" {{\n        label=\"{}\";",
+            cluster.replace("\"", ""),
+            cluster.replace("\"", "")
         )?;
+        match options.cluster_colors.get(cluster) {
+            Some(color) => writeln!(file, "        style=filled;\n        color=\"{}\";", color)?,
+            None => (),
+        }
+        for line in lines {
+            writeln!(file, "        {}", line)?;
+        }
+        writeln!(file, "    }}")?;
+    }
+
+    match &options.ranks {
+        Some(ranks) => {
+            let mut levels: BTreeMap<usize, Vec<&String>> = BTreeMap::new();
+            for i in graph.node_indices() {
+                match ranks.get(&graph[i]) {
+                    Some(&depth) => levels.entry(depth).or_default().push(&ids[&i]),
+                    None => (),
+                }
+            }
+            for (_, level_ids) in levels {
+                let members: Vec<&str> = level_ids.into_iter().map(|id| id.as_str()).collect();
+                writeln!(file, "    {{ rank=same; {}; }}", members.join("; "))?;
+            }
+        }
+        None => (),
     }
 
     writeln!(file)?;
 
-    // Add edges with colors
+    // Add edges
     for e in graph.edge_indices() {
         let (from, to) = graph.edge_endpoints(e).unwrap();
-        let sequence = graph.edge_weight(e).unwrap();
-
-        let color_index =
-            ((sequence - 1) as f32 * (colors.len() - 1) as f32 / (num_calls - 1) as f32) as usize;
-        let color = colors[color_index];
+        let weight = graph[e];
+        let attributes = edge_attributes(&graph[from], &graph[to], weight);
+        let label = match (options.hide_edge_labels, attributes.is_empty()) {
+            (true, _) => String::new(),
+            (false, true) => format!("label=\"{}\"", weight),
+            (false, false) => format!("label=\"{}\", ", weight),
+        };
 
         writeln!(
             file,
-            "    {} -> {} [label=\"{}\", color=\"{}\", fontcolor=\"{}\", penwidth=2.0];",
-            from.index(),
-            to.index(),
-            sequence,
-            color,
-            color
+            "    {} -> {} [{}{}];",
+            ids[&from], ids[&to], label, attributes
         )?;
     }
 
@@ -142,3 +1023,109 @@ pub fn write_dot_file(
 
     Ok(())
 }
+
+// Position of an edge in the color gradient
+fn edge_sequence(
+    graph: &Graph<String, usize, Directed>,
+    edge: EdgeIndex,
+    options: &DotOptions,
+) -> usize {
+    let (_, to) = graph.edge_endpoints(edge).unwrap();
+    match (&options.depths, options.aggregate) {
+        (Some(depths), _) => depths.get(&graph[to]).copied().unwrap_or(1).max(1),
+        (None, true) => edge.index() + 1,
+        (None, false) => graph[edge],
+    }
+}
+
+// Aggregated edges get thicker with their call count, from 1.0 up to 6.0 for the
+// busiest edge; sequence-numbered edges are all drawn alike
+fn edge_penwidth(weight: usize, max_weight: usize, options: &DotOptions) -> f32 {
+    match (options.aggregate, max_weight > 1) {
+        (true, true) => 1.0 + 5.0 * (weight - 1) as f32 / (max_weight - 1) as f32,
+        _ => 2.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph_of(calls: &[(&str, &str)]) -> Graph<String, usize, Directed> {
+        let calls: Vec<(String, String)> = calls
+            .iter()
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .collect();
+        graph_from_calls(&calls)
+    }
+
+    fn edges(graph: &Graph<String, usize, Directed>) -> HashSet<(String, String)> {
+        graph
+            .edge_references()
+            .map(|e| (graph[e.source()].clone(), graph[e.target()].clone()))
+            .collect()
+    }
+
+    #[test]
+    fn merge_by_basename_merges_within_a_crate() {
+        let graph = graph_of(&[("a::main", "a::run"), ("a::main", "a::server::run")]);
+        let merged = merge_by_basename(&graph);
+        assert_eq!(merged.node_count(), 2);
+        assert!(edges(&merged).contains(&("a::main".to_string(), "a::server::run".to_string())));
+    }
+
+    #[test]
+    fn merge_by_basename_keeps_crates_apart() {
+        let graph = graph_of(&[
+            ("a::main", "a::run"),
+            ("a::run", "a::helper"),
+            ("b::run", "b::handle"),
+            ("b::handle", "b::other"),
+        ]);
+        let merged = merge_by_basename(&graph);
+        assert_eq!(edges(&merged), edges(&graph));
+    }
+
+    #[test]
+    fn collapse_chains_merges_only_middle_functions() {
+        let graph = graph_of(&[
+            ("a::main", "a::b"),
+            ("a::other", "a::b"),
+            ("a::b", "a::c"),
+            ("a::c", "a::d"),
+            ("a::c", "a::d"),
+            ("a::d", "a::leaf"),
+        ]);
+        let collapsed = collapse_chains(&graph);
+        let names: HashSet<&str> = collapsed.node_weights().map(|name| name.as_str()).collect();
+        assert_eq!(
+            names,
+            HashSet::from(["a::main", "a::other", "a::b", "a::c → a::d", "a::leaf"])
+        );
+    }
+
+    #[test]
+    fn write_dot_with_replaces_the_default_coloring() {
+        let graph = graph_of(&[("a::main", "a::run")]);
+        let options = DotOptions::default();
+
+        let mut default = Vec::new();
+        write_dot(&graph, &mut default, &options).unwrap();
+        let default = String::from_utf8(default).unwrap();
+        assert!(default.contains("\"a::main\" -> \"a::run\" [label=\"1\", color="));
+
+        let mut custom = Vec::new();
+        write_dot_with(
+            &graph,
+            &mut custom,
+            &options,
+            |node| format!("tooltip=\"{}\"", node),
+            |_, _, weight| format!("penwidth={}", weight),
+        )
+        .unwrap();
+        let custom = String::from_utf8(custom).unwrap();
+        assert!(custom.contains("\"a::run\" [label=\"a::run\", tooltip=\"a::run\"];"));
+        assert!(custom.contains("\"a::main\" -> \"a::run\" [label=\"1\", penwidth=1];"));
+        assert!(!custom.contains("color="));
+    }
+}
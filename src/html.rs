@@ -0,0 +1,97 @@
+use crate::workspace::WorkspaceAnalysis;
+use petgraph::{Graph, prelude::*};
+use std::{error::Error, fs, io::Write};
+
+const TEMPLATE: &str = include_str!("html_template.html");
+
+// A single self-contained page that draws the graph with pan/zoom, search and
+// neighbor highlighting. Nodes that are functions show where they are defined on hover.
+pub fn write_html_file(
+    graph: &Graph<String, usize, Directed>,
+    filename: &str,
+    analysis: &WorkspaceAnalysis,
+) -> Result<(), Box<dyn Error>> {
+    write_html(graph, &mut fs::File::create(filename)?, analysis)
+}
+
+pub fn write_html(
+    graph: &Graph<String, usize, Directed>,
+    file: &mut impl Write,
+    analysis: &WorkspaceAnalysis,
+) -> Result<(), Box<dyn Error>> {
+    let html = TEMPLATE.replace("/*GRAPH_DATA*/", &graph_json(graph, analysis));
+    file.write_all(html.as_bytes())?;
+    Ok(())
+}
+
+fn graph_json(graph: &Graph<String, usize, Directed>, analysis: &WorkspaceAnalysis) -> String {
+    let locations = analysis.locations();
+    let visibilities = analysis.visibilities();
+    let call_lines = analysis.call_lines();
+
+    let nodes: Vec<String> = graph
+        .node_indices()
+        .map(|i| {
+            let name = &graph[i];
+            let mut fields = vec![format!("\"name\":{}", json_string(name))];
+            match locations.get(name) {
+                Some(location) => {
+                    fields.push(format!("\"file\":{}", json_string(&location.file)));
+                    fields.push(format!("\"line\":{}", location.line));
+                }
+                None => (),
+            }
+            match visibilities.get(name) {
+                Some(visibility) => {
+                    fields.push(format!("\"visibility\":{}", json_string(visibility)))
+                }
+                None => (),
+            }
+            format!("{{{}}}", fields.join(","))
+        })
+        .collect();
+
+    let edges: Vec<String> = graph
+        .edge_indices()
+        .map(|e| {
+            let (from, to) = graph.edge_endpoints(e).unwrap();
+            let lines: Vec<String> = call_lines
+                .get(&(graph[from].clone(), graph[to].clone()))
+                .into_iter()
+                .flatten()
+                .map(|line| line.to_string())
+                .collect();
+            format!(
+                "{{\"from\":{},\"to\":{},\"weight\":{},\"lines\":[{}]}}",
+                from.index(),
+                to.index(),
+                graph[e],
+                lines.join(",")
+            )
+        })
+        .collect();
+
+    format!(
+        "{{\"nodes\":[{}],\"edges\":[{}]}}",
+        nodes.join(","),
+        edges.join(",")
+    )
+}
+
+// A JSON string literal that is also safe to embed inside a `<script>` element
+fn json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '<' => escaped.push_str("\\u003c"),
+            '\n' => escaped.push_str("\\n"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
@@ -0,0 +1,12 @@
+#![allow(clippy::single_match)]
+
+pub mod cache;
+pub mod export;
+pub mod git;
+pub mod graph;
+pub mod html;
+pub mod progress;
+pub mod rules;
+pub mod utils;
+pub mod visitor;
+pub mod workspace;
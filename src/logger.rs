@@ -0,0 +1,31 @@
+use ferrisfollow::progress;
+use log::{LevelFilter, Log, Metadata, Record};
+
+// Diagnostics go to stderr so that stdout only carries the actual output
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        progress::suspend(|| match record.level() {
+            log::Level::Info => eprintln!("{}", record.args()),
+            level => eprintln!("{}: {}", level, record.args()),
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+pub fn init(level: LevelFilter) {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
@@ -1,36 +1,878 @@
-mod graph;
-mod utils;
-mod visitor;
+#![allow(clippy::single_match)]
 
-use std::{error::Error, path::Path};
-use walkdir::WalkDir;
+mod cli;
+mod config;
+mod logger;
+mod watch;
+
+use clap::{CommandFactory, FromArgMatches};
+use cli::{Cli, ColorBy, Command, LabelStyle, OutputFormat, SizeBy};
+use ferrisfollow::{
+    export, git,
+    graph::{self, DotOptions, Theme},
+    html,
+    rules::{self, Rule, RuleKind},
+    utils,
+    workspace::{self, AnalysisConfig, EntryKind, WorkspaceAnalysis},
+};
+use petgraph::prelude::*;
+use regex::Regex;
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 fn main() -> Result<(), Box<dyn Error>> {
-    println!(
-        "{} version {}\n a way to explore your Rust program visually\n\n",
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches)?;
+
+    logger::init(match (cli.quiet, cli.verbose) {
+        (true, _) => log::LevelFilter::Error,
+        (false, true) => log::LevelFilter::Debug,
+        (false, false) => log::LevelFilter::Info,
+    });
+
+    log::info!(
+        "{} version {}\n a way to explore your Rust program visually\n",
         env!("CARGO_PKG_NAME"),
         env!("CARGO_PKG_VERSION")
     );
-    let dir = Path::new(".");
-    let visitor = utils::analyze_directory(dir)?;
-    let graph = graph::create_call_graph(&visitor);
+    match config::load(&cli)? {
+        Some(file_config) => config::apply(&mut cli, &matches, file_config),
+        None => (),
+    }
+
+    // Kept until the end of `main`, which is when the worktree is removed again
+    let worktree = match &cli.git_rev {
+        Some(rev) => Some(git::Worktree::checkout(&cli.path, rev)?),
+        None => None,
+    };
+    match &worktree {
+        Some(worktree) => cli.path = worktree.path(),
+        None => (),
+    }
+
+    match &cli.output_dir {
+        Some(dir) => fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create '{}': {}", dir.display(), e))?,
+        None => (),
+    }
+    let output = match cli.output.as_deref() {
+        Some("-") => "-".to_string(),
+        Some(output) => output_path(&cli, output),
+        None => output_path(&cli, &format!("call_graph.{}", cli.format.extension())),
+    };
+    if output == "-"
+        && !matches!(
+            cli.format,
+            OutputFormat::Dot | OutputFormat::Json | OutputFormat::Jsonl | OutputFormat::Adjacency
+        )
+    {
+        return Err(
+            "Writing to stdout ('-') is only supported with --format dot, json, jsonl or adjacency"
+                .into(),
+        );
+    }
+
+    // The output type and file are ferrisfollow's to choose
+    match cli
+        .dot_args
+        .iter()
+        .find(|arg| ["-T", "-o", "-O"].iter().any(|flag| arg.starts_with(flag)))
+    {
+        Some(arg) => return Err(format!("--dot-arg '{}' would override -T or -o", arg).into()),
+        None => (),
+    }
+
+    let rules = match &cli.command {
+        Some(Command::Check { deny, allow }) => Some(parse_rules(deny, allow)?),
+        Some(Command::Neighbors { .. }) | Some(Command::Path { .. }) | None => None,
+    };
+
+    let mut config = AnalysisConfig::default();
+    if !cli.start_functions.is_empty() {
+        config.start_functions = Some(cli.start_functions.clone());
+    }
+    config.entry_mode = cli.entries;
+    match &cli.root {
+        Some(root) => config.start_functions = Some(vec![root.clone()]),
+        None => (),
+    }
+    config.respect_ignore_files = !cli.no_ignore;
+    config.include_benches = cli.include_benches;
+    config.include_tests = cli.include_tests;
+    config.include_examples = cli.include_examples;
+    config.include_external_crates = cli.include_external_crates;
+    config.exclude_crates = cli.exclude_crates.clone();
+    config.progress = !cli.quiet;
+    config.include_std = cli.include_std;
+    config.max_depth = cli.depth;
+    config.only = match &cli.only {
+        Some(pattern) => Some(Regex::new(pattern)?),
+        None => None,
+    };
+    // Patterns are checked before the analysis rather than once it is done
+    for pattern in &cli.exclude_patterns {
+        Regex::new(pattern)?;
+    }
+    if cli.cache {
+        let root = match cli.path.is_file() {
+            true => cli.path.parent().unwrap_or(Path::new(".")),
+            false => &cli.path,
+        };
+        config.cache_dir = Some(root.join(".ferrisfollow").join("cache"));
+    }
+
+    match &cli.command {
+        Some(Command::Neighbors { function, hops }) => {
+            return print_neighbors(&analyze(&cli, &config)?, function, *hops);
+        }
+        Some(Command::Path { from, to }) => {
+            return print_path(&analyze(&cli, &config)?, from, to);
+        }
+        _ => (),
+    }
+
+    match rules {
+        Some(rules) => {
+            let passed = check(&analyze(&cli, &config)?, &rules);
+            // `process::exit` skips destructors, so clean up first
+            drop(worktree);
+            match passed {
+                true => return Ok(()),
+                false => process::exit(1),
+            }
+        }
+        None => (),
+    }
+
+    match cli.watch {
+        true => watch::watch(&roots(&cli), |cancel| {
+            generate(&cli, &config, &output, cancel)
+        }),
+        false => generate(&cli, &config, &output, &AtomicBool::new(false)),
+    }
+}
+
+fn analyze(cli: &Cli, config: &AnalysisConfig) -> Result<WorkspaceAnalysis, Box<dyn Error>> {
+    match (cli.path.is_file(), cli.more_paths.is_empty()) {
+        (true, true) => workspace::analyze_file(&cli.path, config),
+        (true, false) => Err("Only directories can be analyzed together, not single files".into()),
+        (false, true) => workspace::analyze_repository(&cli.path, config),
+        (false, false) => workspace::analyze_repositories(&roots(cli), config),
+    }
+}
+
+// The analyzed directory and every one given with `--also`
+fn roots(cli: &Cli) -> Vec<PathBuf> {
+    std::iter::once(cli.path.clone())
+        .chain(cli.more_paths.iter().cloned())
+        .collect()
+}
+
+// Analyzes the code and writes the summary and every requested output. Image rendering
+// is abandoned as soon as `cancel` is set.
+fn generate(
+    cli: &Cli,
+    config: &AnalysisConfig,
+    output: &str,
+    cancel: &AtomicBool,
+) -> Result<(), Box<dyn Error>> {
+    let analysis = analyze(cli, config)?;
+    let graph = simplify(
+        cli,
+        &analysis,
+        match cli.modules {
+            true => analysis.collapse_to_modules(),
+            false => graph::create_combined_graph(&analysis),
+        },
+    )?;
+
+    // Keep stdout clean for the graph itself when it is streamed there
+    let mut summary: Box<dyn Write> = match output {
+        "-" => Box::new(io::stderr()),
+        _ => Box::new(io::stdout()),
+    };
+
+    writeln!(summary, "Crates: {}", analysis.crate_names.join(", "))?;
+    writeln!(summary, "Files analyzed: {}", analysis.files_analyzed())?;
+    writeln!(
+        summary,
+        "Entry points: {}",
+        analysis.get_entry_points().join(", ")
+    )?;
+
+    if !analysis.test_entry_points.is_empty() {
+        writeln!(
+            summary,
+            "Test entry points: {}",
+            analysis.test_entry_points.join(", ")
+        )?;
+    }
+    if !analysis.bench_entry_points.is_empty() {
+        writeln!(
+            summary,
+            "Bench entry points: {}",
+            analysis.bench_entry_points.join(", ")
+        )?;
+    }
+
+    let calls: HashSet<(NodeIndex, NodeIndex)> = graph
+        .edge_references()
+        .map(|e| (e.source(), e.target()))
+        .collect();
+    writeln!(
+        summary,
+        "Graph: {} functions, {} calls, {} calls deep at most",
+        graph
+            .node_weights()
+            .filter(|name| !analysis.is_initializer(name))
+            .count(),
+        calls.len(),
+        graph::call_depths(&graph, &analysis.followed_entries())
+            .values()
+            .max()
+            .copied()
+            .unwrap_or(0)
+    )?;
+    match graph::largest_component_size(&graph) {
+        1 => writeln!(summary, "Largest strongly connected component: 1 function")?,
+        size => writeln!(
+            summary,
+            "Largest strongly connected component: {} functions",
+            size
+        )?,
+    }
+
+    // Call targets that no definition was found for, other than the standard library's.
+    // Module and type nodes are never definitions, so they aren't counted. A collapsed
+    // chain is checked function by function.
+    let std_calls = analysis.std_calls();
+    let phantom_functions: Vec<&str> = match cli.modules || cli.merge_impls {
+        true => Vec::new(),
+        false => graph
+            .node_weights()
+            .flat_map(|name| graph::chain_members(name))
+            .filter(|name| !analysis.is_defined(name) && !std_calls.contains(*name))
+            .collect(),
+    };
+    if !phantom_functions.is_empty() {
+        writeln!(
+            summary,
+            "Phantom functions (called but never defined): {}",
+            phantom_functions.len()
+        )?;
+    }
+
+    match analysis.resolved_calls() {
+        (_, 0) => (),
+        (resolved, total) => writeln!(
+            summary,
+            "Resolved calls: {} / {} ({}%)",
+            resolved,
+            total,
+            resolved * 100 / total
+        )?,
+    }
+    match analysis.outside_calls() {
+        0 => (),
+        outside => writeln!(
+            summary,
+            "Calls into std and unanalyzed dependencies (not counted above): {}",
+            outside
+        )?,
+    }
+
+    let cross_crate_calls = analysis.get_cross_crate_calls();
+    if !cross_crate_calls.is_empty() {
+        writeln!(summary, "Cross-crate calls:")?;
+        for (caller, callee) in &cross_crate_calls {
+            writeln!(summary, "  {} -> {}", caller, callee)?;
+        }
+    }
+
+    let mutual_recursion = analysis.mutual_recursion_pairs();
+    if !mutual_recursion.is_empty() {
+        writeln!(summary, "Mutually recursive functions:")?;
+        for (a, b) in &mutual_recursion {
+            writeln!(summary, "  {} <-> {}", a, b)?;
+        }
+    }
+
+    let ffi_functions = analysis.ffi_functions();
+    if !ffi_functions.is_empty() {
+        writeln!(summary, "FFI functions: {}", ffi_functions.join(", "))?;
+    }
+
+    let async_functions = analysis.async_functions();
+    if !async_functions.is_empty() {
+        writeln!(summary, "Async functions: {}", async_functions.len())?;
+    }
+
+    let unsafe_functions = analysis.unsafe_functions();
+    if !unsafe_functions.is_empty() {
+        writeln!(
+            summary,
+            "Unsafe functions (unsafe fn or unsafe blocks): {}",
+            unsafe_functions.len()
+        )?;
+    }
+
+    let most_complex = analysis.most_complex_functions(cli.most_complex);
+    if !most_complex.is_empty() {
+        writeln!(summary, "Most complex functions:")?;
+        for (name, complexity) in &most_complex {
+            writeln!(summary, "  {} ({})", name, complexity)?;
+        }
+    }
+
+    let mut module_sizes: Vec<(String, usize)> =
+        analysis.functions_per_module().into_iter().collect();
+    module_sizes.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    if cli.largest_modules > 0 && !module_sizes.is_empty() {
+        writeln!(summary, "Largest modules (functions):")?;
+        for (module, count) in module_sizes.iter().take(cli.largest_modules) {
+            writeln!(summary, "  {} ({})", module, count)?;
+        }
+    }
+
+    match cli.coupling {
+        Some(limit) => {
+            let coupling = analysis.module_coupling();
+            if !coupling.is_empty() {
+                writeln!(summary, "Most coupled modules (calls in either direction):")?;
+                for (a, b, calls) in coupling.iter().take(limit) {
+                    writeln!(summary, "  {} <-> {}: {}", a, b, calls)?;
+                }
+            }
+        }
+        None => (),
+    }
+
+    if cli.method_collisions {
+        let collisions = analysis.method_collisions();
+        if !collisions.is_empty() {
+            writeln!(summary, "Methods defined on several types:")?;
+            for (method_name, types) in &collisions {
+                writeln!(
+                    summary,
+                    "  {} ({}): {}",
+                    method_name,
+                    types.len(),
+                    types.join(", ")
+                )?;
+            }
+        }
+    }
+
+    if cli.instantiations {
+        let instantiations = analysis.generic_instantiations();
+        if !instantiations.is_empty() {
+            writeln!(summary, "Generic instantiations:")?;
+            for (name, type_arguments) in &instantiations {
+                writeln!(
+                    summary,
+                    "  {} ({}): {}",
+                    name,
+                    type_arguments.len(),
+                    type_arguments.join(", ")
+                )?;
+            }
+        }
+    }
+
+    if cli.toposort {
+        match graph::topological_order(&graph) {
+            Ok(order) => {
+                writeln!(summary, "Topological order (callers first):")?;
+                for name in &order {
+                    writeln!(summary, "  {}", name)?;
+                }
+            }
+            Err(in_cycle) => writeln!(
+                summary,
+                "No topological order: '{}' is part of a call cycle",
+                in_cycle
+            )?,
+        }
+    }
+
+    if cli.summary_only {
+        return check_resolved(cli, &analysis);
+    }
+
+    let mut dot_options = DotOptions {
+        aggregate: cli.modules || cli.merge_impls,
+        palette: cli.palette,
+        theme: cli.theme,
+        hide_edge_labels: cli.no_edge_labels,
+        title: match cli.no_title {
+            true => None,
+            false => Some(format!(
+                "{} ({}, ferrisfollow {})",
+                analysis.crate_names.join(", "),
+                utils::today(),
+                env!("CARGO_PKG_VERSION")
+            )),
+        },
+        ..Default::default()
+    };
+    if cli.labels == LabelStyle::Signature {
+        dot_options.node_labels = analysis
+            .signatures()
+            .into_iter()
+            .map(|(name, signature)| (name.clone(), format!("{}{}", name, signature)))
+            .collect();
+    }
+    if cli.reach {
+        for (name, count) in analysis.reach_counts() {
+            let label = dot_options
+                .node_labels
+                .remove(&name)
+                .unwrap_or(name.clone());
+            dot_options
+                .node_labels
+                .insert(name, format!("{} (reaches {})", label, count));
+        }
+    }
+    if !cli.strip_prefixes.is_empty() {
+        for name in graph.node_weights() {
+            let label = dot_options.node_labels.get(name).unwrap_or(name);
+            match cli
+                .strip_prefixes
+                .iter()
+                .find_map(|prefix| label.strip_prefix(prefix.as_str()))
+            {
+                Some(stripped) if !stripped.is_empty() => {
+                    let stripped = stripped.to_string();
+                    dot_options.node_labels.insert(name.clone(), stripped);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    let mut truncated = Vec::new();
+    match cli.max_label_len {
+        Some(max_len) => {
+            for name in graph.node_weights() {
+                let label = dot_options.node_labels.get(name).unwrap_or(name);
+                let shortened = utils::truncate_label(label, max_len);
+                if shortened != *label {
+                    dot_options.node_labels.insert(name.clone(), shortened);
+                    truncated.push(name.clone());
+                }
+            }
+        }
+        None => (),
+    }
+
+    // Test and example code each get a box of their own, apart from production code
+    let kinds: Vec<(&String, &str)> = graph
+        .node_weights()
+        .map(
+            |name| match (utils::is_test_module(name), utils::is_example_module(name)) {
+                (true, _) => (name, "tests"),
+                (false, true) => (name, "examples"),
+                (false, false) => (name, "production"),
+            },
+        )
+        .collect();
+    if kinds.iter().any(|&(_, kind)| kind != "production") {
+        dot_options.clusters = kinds
+            .into_iter()
+            .map(|(name, kind)| (name.clone(), kind.to_string()))
+            .collect();
+    }
+    if cli.cluster_colors {
+        let (tests, examples) = match cli.theme {
+            Theme::Light => ("#fef9c3", "#e0f2fe"),
+            Theme::Dark => ("#422006", "#082f49"),
+        };
+        dot_options.cluster_colors = HashMap::from([
+            ("tests".to_string(), tests.to_string()),
+            ("examples".to_string(), examples.to_string()),
+        ]);
+    }
+
+    for (name, kind) in analysis.entry_points() {
+        match kind {
+            EntryKind::Test | EntryKind::Bench => {
+                dot_options.add_node_attribute(&name, "shape=house".to_string())
+            }
+            EntryKind::Main => (),
+        }
+    }
+    // A shortened label's full name goes first in its tooltip
+    let mut tooltips = analysis.docs();
+    for name in truncated {
+        let tooltip = match tooltips.remove(&name) {
+            Some(doc) => format!("{}\n{}", name, doc),
+            None => name.clone(),
+        };
+        tooltips.insert(name, tooltip);
+    }
+    for (name, tooltip) in tooltips {
+        let tooltip = tooltip.replace('\\', "\\\\").replace('"', "\\\"");
+        dot_options.add_node_attribute(&name, format!("tooltip=\"{}\"", tooltip));
+    }
+    for name in &ffi_functions {
+        dot_options.add_node_attribute(name, "shape=diamond".to_string());
+    }
+    for name in &async_functions {
+        dot_options.add_node_attribute(name, "style=rounded".to_string());
+    }
+    for name in &unsafe_functions {
+        dot_options.add_node_attribute(name, "color=\"#dc2626\", penwidth=3.0".to_string());
+    }
+    for name in std_calls
+        .iter()
+        .map(String::as_str)
+        .chain(phantom_functions)
+    {
+        dot_options.add_node_attribute(name, "style=dashed".to_string());
+    }
+    // Calls through a trait object may end up in any of the trait's implementations
+    for (from, to) in analysis.possible_calls() {
+        dot_options.add_edge_attribute(&from, &to, "style=dashed".to_string());
+    }
+    match cli.size_by {
+        Some(SizeBy::Loc) => {
+            for (name, size) in graph::size_attributes(&analysis.lines_of_code()) {
+                dot_options.add_node_attribute(&name, size);
+            }
+        }
+        None => (),
+    }
+    match cli.color_by {
+        Some(ColorBy::Complexity) => {
+            for (name, fill) in graph::heat_attributes(&analysis.complexity(), cli.palette) {
+                dot_options.add_node_attribute(&name, fill);
+            }
+        }
+        Some(ColorBy::Depth) => {
+            dot_options.depths = Some(graph::call_depths(&graph, &analysis.followed_entries()))
+        }
+        None => (),
+    }
+    if cli.rank_by_depth {
+        dot_options.ranks = Some(graph::call_depths(&graph, &analysis.followed_entries()));
+    }
+
+    if cli.format == OutputFormat::Svg {
+        let links = graph::source_link_attributes(
+            &analysis.locations(),
+            &roots(cli),
+            cli.link_base.as_deref(),
+        );
+        for (name, link) in links {
+            dot_options.add_node_attribute(&name, link);
+        }
+    }
+
+    let graphviz_installed = utils::check_graphviz_installed();
+
+    match cli.max_nodes_per_image {
+        Some(max_nodes) if graph.node_count() > max_nodes => {
+            if output == "-" {
+                return Err("--max-nodes-per-image needs an output file, not stdout ('-')".into());
+            }
+            let (stem, extension) = match output.rsplit_once('.') {
+                Some((stem, extension)) => (stem, format!(".{}", extension)),
+                None => (output, String::new()),
+            };
+
+            let mut index = String::new();
+            for (i, part) in graph::partition(&graph, max_nodes).iter().enumerate() {
+                let part_output = format!("{}_{}{}", stem, i + 1, extension);
+                render(
+                    part,
+                    &part_output,
+                    cli,
+                    &analysis,
+                    &dot_options,
+                    graphviz_installed,
+                    cancel,
+                )?;
+
+                let mut names: Vec<&String> = part.node_weights().collect();
+                names.sort();
+                index.push_str(&format!("{}:\n", part_output));
+                for name in names {
+                    index.push_str(&format!("  {}\n", name));
+                }
+            }
+
+            let index_file = format!("{}_index.txt", stem);
+            fs::write(&index_file, index)?;
+            log::info!("Listed the functions of each part in '{}'", index_file);
+        }
+        _ => render(
+            &graph,
+            output,
+            cli,
+            &analysis,
+            &dot_options,
+            graphviz_installed,
+            cancel,
+        )?,
+    }
+
+    match &cli.call_counts {
+        Some(path) => {
+            let path = output_path(cli, &path.to_string_lossy());
+            export::write_call_counts_file(&graph, &path)?;
+            log::info!("Wrote call counts to '{}'", path);
+        }
+        None => (),
+    }
+
+    if cli.per_crate {
+        for crate_name in &analysis.crate_names {
+            let crate_graph = simplify(
+                cli,
+                &analysis,
+                graph::create_crate_graph(&analysis, crate_name),
+            )?;
+            let crate_output =
+                output_path(cli, &format!("{}.{}", crate_name, cli.format.extension()));
+            render(
+                &crate_graph,
+                &crate_output,
+                cli,
+                &analysis,
+                &dot_options,
+                graphviz_installed,
+                cancel,
+            )?;
+        }
+    }
+
+    if cli.format.is_image() && !cli.dot_only && !graphviz_installed {
+        log::warn!(
+            "Graphviz (dot) is not installed. Only DOT files were generated.\n\
+             Install Graphviz to automatically generate PNG, SVG and PDF visualizations."
+        );
+    }
+
+    check_resolved(cli, &analysis)
+}
+
+// With `--strict`, fails on any call into a workspace crate that didn't resolve
+fn check_resolved(cli: &Cli, analysis: &WorkspaceAnalysis) -> Result<(), Box<dyn Error>> {
+    let unresolved = analysis.unresolved_crate_calls();
+    match (cli.strict, unresolved.is_empty()) {
+        (true, false) => {
+            for (caller, path) in &unresolved {
+                log::error!("'{}' calls '{}', which was not found", caller, path);
+            }
+            Err(format!(
+                "{} call(s) into workspace crates could not be resolved",
+                unresolved.len()
+            )
+            .into())
+        }
+        _ => Ok(()),
+    }
+}
+
+// Applies the options that thin out the graph before it is drawn
+fn simplify(
+    cli: &Cli,
+    analysis: &WorkspaceAnalysis,
+    mut graph: Graph<String, usize, Directed>,
+) -> Result<Graph<String, usize, Directed>, Box<dyn Error>> {
+    match &cli.root {
+        Some(root) => graph = graph::reachable_subgraph(&graph, &analysis.find_function(root)?),
+        None => (),
+    }
+    if !cli.exclude_attrs.is_empty() || !cli.only_attrs.is_empty() {
+        let attributes = analysis.attributes();
+        let has_any = |name: &str, wanted: &[String]| {
+            attributes.get(name).is_some_and(|paths| {
+                paths.iter().any(|path| {
+                    wanted
+                        .iter()
+                        .any(|attribute| utils::attribute_matches(path, attribute))
+                })
+            })
+        };
+        graph = graph::filter_nodes(&graph, |name| {
+            !has_any(name, &cli.exclude_attrs)
+                && (cli.only_attrs.is_empty() || has_any(name, &cli.only_attrs))
+        });
+    }
+    if cli.merge_impls {
+        graph = graph::merge_impls(&graph, &analysis.method_types());
+    }
+    if !cli.exclude_patterns.is_empty() {
+        let patterns = cli
+            .exclude_patterns
+            .iter()
+            .map(|pattern| Regex::new(pattern))
+            .collect::<Result<Vec<_>, _>>()?;
+        graph = graph::remove_matching(&graph, &patterns);
+    }
+    if cli.merge_by_basename {
+        graph = graph::merge_by_basename(&graph);
+    }
+    if cli.hide_leaves {
+        graph = graph::hide_leaves(&graph);
+    }
+    if cli.collapse_chains {
+        graph = graph::collapse_chains(&graph);
+    }
+    Ok(match cli.top {
+        Some(limit) => graph::keep_most_connected(&graph, limit),
+        None => graph,
+    })
+}
 
-    let dot_file = "call_graph.dot";
-    let png_file = "call_graph.png";
+// `file` inside `--output-dir`, if one was given
+fn output_path(cli: &Cli, file: &str) -> String {
+    match &cli.output_dir {
+        Some(dir) => dir.join(file).to_string_lossy().into_owned(),
+        None => file.to_string(),
+    }
+}
 
-    graph::write_dot_file(&graph, dot_file)?;
-    println!("Generated call graph in '{}'", dot_file);
+fn render(
+    graph: &Graph<String, usize, Directed>,
+    output: &str,
+    cli: &Cli,
+    analysis: &WorkspaceAnalysis,
+    dot_options: &DotOptions,
+    graphviz_installed: bool,
+    cancel: &AtomicBool,
+) -> Result<(), Box<dyn Error>> {
+    let format = cli.format;
+    // The page draws the graph itself, so it needs neither a DOT file nor Graphviz
+    if format == OutputFormat::Html {
+        html::write_html_file(graph, output, analysis)?;
+        log::info!("Generated interactive call graph in '{}'", output);
+        return Ok(());
+    }
+    if format == OutputFormat::Jsonl {
+        export::write_jsonl_file(graph, output, analysis)?;
+        match output {
+            "-" => log::info!("Wrote call graph to stdout"),
+            _ => log::info!("Generated call graph in '{}'", output),
+        }
+        return Ok(());
+    }
+    if format == OutputFormat::Adjacency {
+        export::write_adjacency_file(graph, output)?;
+        match output {
+            "-" => log::info!("Wrote call graph to stdout"),
+            _ => log::info!("Generated call graph in '{}'", output),
+        }
+        return Ok(());
+    }
+    if format == OutputFormat::Json {
+        export::write_json_file(graph, output, analysis)?;
+        match output {
+            "-" => log::info!("Wrote call graph to stdout"),
+            _ => log::info!("Generated call graph in '{}'", output),
+        }
+        return Ok(());
+    }
+
+    // Images are rendered from a DOT file written next to them
+    let dot_file = match format {
+        OutputFormat::Dot => output.to_string(),
+        _ => Path::new(output)
+            .with_extension("dot")
+            .to_string_lossy()
+            .into_owned(),
+    };
 
-    if !utils::check_graphviz_installed() {
-        println!("Warning: Graphviz (dot) is not installed. Only DOT file will be generated.");
-        println!("Install Graphviz to automatically generate PNG visualizations.");
+    match cli.collapse_chains {
+        true => graph::write_dot_file(graph, &dot_file, &dot_options.with_chains(graph))?,
+        false => graph::write_dot_file(graph, &dot_file, dot_options)?,
+    }
+    match dot_file.as_str() {
+        "-" => log::info!("Wrote call graph to stdout"),
+        _ => log::info!("Generated call graph in '{}'", dot_file),
+    }
+
+    if format == OutputFormat::Dot || cli.dot_only || !graphviz_installed {
         return Ok(());
     }
 
-    match utils::generate_png(dot_file, png_file) {
-        Ok(_) => println!("Generated PNG visualization in '{}'", png_file),
-        Err(e) => println!("Failed to generate PNG: {}. Is Graphviz installed?", e),
+    let image_type = format.extension();
+    match utils::generate_image(&dot_file, output, image_type, &cli.dot_args, cancel) {
+        Ok(_) if cancel.load(Ordering::Relaxed) => {
+            log::info!("Stopped rendering '{}' for newer changes", output)
+        }
+        Ok(_) => log::info!(
+            "Generated {} visualization in '{}'",
+            image_type.to_uppercase(),
+            output
+        ),
+        Err(e) => log::error!("{}. Is Graphviz installed?", e),
     }
 
     Ok(())
 }
+
+fn parse_rules(deny: &[String], allow: &[String]) -> Result<Vec<Rule>, Box<dyn Error>> {
+    if deny.is_empty() {
+        log::warn!("No --deny rules given, so nothing can be violated");
+    }
+
+    let deny_rules = deny.iter().map(|spec| Rule::parse(RuleKind::Deny, spec));
+    let allow_rules = allow.iter().map(|spec| Rule::parse(RuleKind::Allow, spec));
+    deny_rules.chain(allow_rules).collect()
+}
+
+// Prints the functions within `hops` calls of `function`, further ones indented deeper
+fn print_neighbors(
+    analysis: &WorkspaceAnalysis,
+    function: &str,
+    hops: usize,
+) -> Result<(), Box<dyn Error>> {
+    let function = analysis.find_function(function)?;
+    let callers = analysis.callers_within(&function, hops);
+    let callees = analysis.callees_within(&function, hops);
+
+    for (heading, neighbors) in [("Callers", callers), ("Callees", callees)] {
+        match neighbors.is_empty() {
+            true => println!("{} of {}: none", heading, function),
+            false => println!("{} of {}:", heading, function),
+        }
+        for (name, distance) in &neighbors {
+            println!("{}{}", "  ".repeat(*distance), name);
+        }
+    }
+    Ok(())
+}
+
+fn print_path(analysis: &WorkspaceAnalysis, from: &str, to: &str) -> Result<(), Box<dyn Error>> {
+    let (from, to) = (analysis.find_function(from)?, analysis.find_function(to)?);
+    match analysis.shortest_path(&from, &to) {
+        Some(path) => println!("{}", path.join(" -> ")),
+        None => println!("{} never calls {}, directly or indirectly", from, to),
+    }
+    Ok(())
+}
+
+// Prints any violations, returning whether there were none
+fn check(analysis: &WorkspaceAnalysis, rules: &[Rule]) -> bool {
+    let violations = rules::find_violations(rules, &analysis.function_calls());
+
+    if violations.is_empty() {
+        println!("No architecture rule violations");
+        return true;
+    }
+
+    println!("{} architecture rule violation(s):", violations.len());
+    for (caller, callee) in &violations {
+        println!("  {} -> {}", caller, callee);
+    }
+    false
+}
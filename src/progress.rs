@@ -0,0 +1,62 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use std::{io::IsTerminal, sync::Mutex};
+
+// The bar being drawn, if any, so that log output can be printed above it
+static ACTIVE: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+// A progress bar on stderr, drawn only when stderr is a terminal and removed again on
+// drop
+pub struct Progress {
+    bar: ProgressBar,
+}
+
+impl Progress {
+    pub fn start(enabled: bool, total: usize) -> Self {
+        let bar = match enabled && std::io::stderr().is_terminal() {
+            true => ProgressBar::new(total as u64),
+            false => ProgressBar::hidden(),
+        };
+        match ProgressStyle::with_template("[{bar:30}] {pos}/{len} {wide_msg}") {
+            Ok(style) => bar.set_style(style.progress_chars("=> ")),
+            Err(_) => (),
+        }
+
+        match ACTIVE.lock() {
+            Ok(mut active) => *active = Some(bar.clone()),
+            Err(_) => (),
+        }
+        Progress { bar }
+    }
+
+    // Names what is being worked on now
+    pub fn set_message(&self, message: String) {
+        self.bar.set_message(message);
+    }
+
+    pub fn inc(&self) {
+        self.bar.inc(1);
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.bar.finish_and_clear();
+        match ACTIVE.lock() {
+            Ok(mut active) => *active = None,
+            Err(_) => (),
+        }
+    }
+}
+
+// Runs `f` with the progress bar, if one is drawn, hidden, so that whatever `f` writes
+// to the terminal doesn't end up mixed into it
+pub fn suspend<R>(f: impl FnOnce() -> R) -> R {
+    let active = match ACTIVE.lock() {
+        Ok(active) => active.clone(),
+        Err(_) => None,
+    };
+    match active {
+        Some(bar) => bar.suspend(f),
+        None => f(),
+    }
+}
@@ -0,0 +1,84 @@
+use std::error::Error;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RuleKind {
+    Allow,
+    Deny,
+}
+
+// An architecture rule such as `api::* -> db::internal::*`. Patterns are globs where
+// `*` matches anything (including `::`), compared against the full function name and
+// against the name without its crate prefix.
+#[derive(Clone, Debug)]
+pub struct Rule {
+    pub kind: RuleKind,
+    pub source: String,
+    pub target: String,
+}
+
+impl Rule {
+    // Parses `<source pattern> -> <target pattern>`
+    pub fn parse(kind: RuleKind, spec: &str) -> Result<Rule, Box<dyn Error>> {
+        match spec.split_once("->") {
+            Some((source, target)) if !source.trim().is_empty() && !target.trim().is_empty() => {
+                Ok(Rule {
+                    kind,
+                    source: source.trim().to_string(),
+                    target: target.trim().to_string(),
+                })
+            }
+            _ => Err(format!("Invalid rule '{}', expected '<source> -> <target>'", spec).into()),
+        }
+    }
+
+    pub fn matches(&self, caller: &str, callee: &str) -> bool {
+        matches_name(&self.source, caller) && matches_name(&self.target, callee)
+    }
+}
+
+// Calls matched by a deny rule and not by any allow rule, sorted and without repeats
+pub fn find_violations(
+    rules: &[Rule],
+    function_calls: &[(String, String)],
+) -> Vec<(String, String)> {
+    let applies = |kind: RuleKind, caller: &str, callee: &str| {
+        rules
+            .iter()
+            .any(|rule| rule.kind == kind && rule.matches(caller, callee))
+    };
+
+    let mut violations: Vec<(String, String)> = function_calls
+        .iter()
+        .filter(|(caller, callee)| {
+            applies(RuleKind::Deny, caller, callee) && !applies(RuleKind::Allow, caller, callee)
+        })
+        .cloned()
+        .collect();
+    violations.sort();
+    violations.dedup();
+    violations
+}
+
+fn matches_name(pattern: &str, name: &str) -> bool {
+    glob_match(pattern, name)
+        || name
+            .split_once("::")
+            .is_some_and(|(_, without_crate)| glob_match(pattern, without_crate))
+}
+
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((prefix, rest)) => {
+            let text = match text.strip_prefix(prefix) {
+                Some(text) => text,
+                None => return false,
+            };
+            // Let the `*` swallow every possible number of characters
+            text.char_indices()
+                .map(|(i, _)| i)
+                .chain(std::iter::once(text.len()))
+                .any(|i| glob_match(rest, &text[i..]))
+        }
+    }
+}
@@ -1,23 +1,92 @@
 use crate::visitor::FunctionCallVisitor;
-use std::{error::Error, path::Path, process::Command};
-use walkdir::WalkDir;
+use ignore::WalkBuilder;
+use quote::ToTokens;
+use std::{
+    error::Error,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-pub fn generate_png(dot_file: &str, png_file: &str) -> Result<(), Box<dyn Error>> {
-    let output = Command::new("dot")
-        .arg("-Tpng")
+// Renders `dot_file` with Graphviz into an image of the given type, e.g. `png` or `svg`,
+// passing `extra_args` on to it. Graphviz is killed, and the image left unfinished,
+// once `cancel` is set.
+pub fn generate_image(
+    dot_file: &str,
+    image_file: &str,
+    image_type: &str,
+    extra_args: &[String],
+    cancel: &AtomicBool,
+) -> Result<(), Box<dyn Error>> {
+    let mut child = Command::new("dot")
+        .arg(format!("-T{}", image_type))
         .arg(dot_file)
+        .args(extra_args)
         .arg("-o")
-        .arg(png_file)
-        .output()?;
+        .arg(image_file)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
 
+    while child.try_wait()?.is_none() {
+        if cancel.load(Ordering::Relaxed) {
+            child.kill()?;
+            child.wait()?;
+            return Ok(());
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    let output = child.wait_with_output()?;
     if !output.status.success() {
         let error = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("Failed to generate PNG: {}", error).into());
+        return Err(format!(
+            "Failed to generate {}: {}",
+            image_type.to_uppercase(),
+            error
+        )
+        .into());
     }
 
     Ok(())
 }
 
+// Renders DOT source with Graphviz into an image of the given type, e.g. `png`, piping it
+// through `dot` so that nothing is written to disk
+pub fn render_dot_to_bytes(dot: &str, image_type: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut child = Command::new("dot")
+        .arg(format!("-T{}", image_type))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Written from another thread, as `dot` may fill its stdout before reading all of it
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or("Failed to open Graphviz's stdin")?;
+    let dot = dot.to_string();
+    let writer = thread::spawn(move || stdin.write_all(dot.as_bytes()));
+
+    let output = child.wait_with_output()?;
+    writer.join().map_err(|_| "Failed to write to Graphviz")??;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "Failed to generate {}: {}",
+            image_type.to_uppercase(),
+            error
+        )
+        .into());
+    }
+
+    Ok(output.stdout)
+}
+
 pub fn check_graphviz_installed() -> bool {
     Command::new("dot")
         .arg("-V")
@@ -26,37 +95,283 @@ pub fn check_graphviz_installed() -> bool {
         .unwrap_or(false)
 }
 
-pub fn analyze_directory(dir: &Path) -> Result<FunctionCallVisitor, Box<dyn Error>> {
-    let mut visitor = FunctionCallVisitor::default();
+pub fn analyze_directory(
+    visitor: &mut FunctionCallVisitor,
+    crate_name: &str,
+    dir: &Path,
+    respect_ignore_files: bool,
+) -> Result<(), Box<dyn Error>> {
+    // Every module of a crate is rooted at the crate name
+    visitor.current_module = vec![crate_name.to_string()];
+    visitor.loaded_crates.insert(crate_name.to_string());
 
-    // First, process lib.rs if it exists
-    let lib_path = dir.join("src/lib.rs");
-    if lib_path.exists() {
-        visitor.process_module(&lib_path)?;
+    // lib.rs comes first, then main.rs, then all other .rs files, skipping hidden ones
+    // and anything .gitignore/.ignore excludes unless ignore files are disabled
+    let mut files: Vec<PathBuf> = ["src/lib.rs", "src/main.rs"]
+        .iter()
+        .map(|root| dir.join(root))
+        .filter(|root| root.exists())
+        .collect();
+    files.extend(
+        WalkBuilder::new(dir.join("src"))
+            .git_ignore(respect_ignore_files)
+            .git_global(respect_ignore_files)
+            .git_exclude(respect_ignore_files)
+            .ignore(respect_ignore_files)
+            .hidden(respect_ignore_files)
+            .parents(respect_ignore_files)
+            .build()
+            .filter_map(|e| e.ok())
+            .filter(|e| {
+                e.path().extension().is_some_and(|ext| ext == "rs")
+                    && (e
+                        .path()
+                        .file_name()
+                        .is_some_and(|name| name != "main.rs" && name != "lib.rs")
+                        || binary_directory(e.path()).is_some())
+            })
+            .map(|e| e.into_path()),
+    );
+
+    // Every `#[path]` module is named before any file is, so the walk order can't decide
+    // whether a file gets its declared name or its file stem
+    for file in &files {
+        visitor.declare_path_modules(file);
+    }
+    for file in &files {
+        visitor.process_module(file)?;
     }
 
-    // Then process main.rs
-    let main_path = dir.join("src/main.rs");
-    if main_path.exists() {
-        visitor.process_module(&main_path)?;
+    visitor.current_module.clear();
+
+    Ok(())
+}
+
+// The binary a `src/bin/<name>/main.rs` is the root of, whose `main` must not be taken
+// for the crate's own `src/main.rs`
+pub fn binary_directory(path: &Path) -> Option<String> {
+    let names: Vec<&std::ffi::OsStr> = path.iter().rev().take(4).collect();
+    match names.as_slice() {
+        [file, binary, bin, src] if *file == "main.rs" && *bin == "bin" && *src == "src" => {
+            Some(binary.to_string_lossy().into_owned())
+        }
+        _ => None,
     }
+}
+
+// Today's date (UTC) as `YYYY-MM-DD`
+pub fn today() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+
+    // Days since 1970-01-01 to a civil date, after Howard Hinnant's `civil_from_days`
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = match shifted_month < 10 {
+        true => shifted_month + 3,
+        false => shifted_month - 9,
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// Crate names are written with underscores in paths, e.g. `my-crate` is `my_crate::`
+pub fn normalize_crate_name(name: &str) -> String {
+    name.replace('-', "_")
+}
 
-    // Process all other .rs files
-    for entry in WalkDir::new(dir.join("src"))
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| {
-            e.path().extension().map_or(false, |ext| ext == "rs")
-                && e.path()
-                    .file_name()
-                    .map_or(false, |name| name != "main.rs" && name != "lib.rs")
+// Whether a crate-prefixed function is test code: in an integration test (`tests::`) or
+// a unit test module like `mymod::tests`
+pub fn is_test_module(name: &str) -> bool {
+    name.split("::")
+        .skip(1)
+        .any(|segment| segment == "tests" || segment == "test")
+}
+
+// Whether a crate-prefixed function is in an example (`examples::`)
+pub fn is_example_module(name: &str) -> bool {
+    name.split("::").nth(1) == Some("examples")
+}
+
+// The crate a crate-prefixed function name belongs to
+pub fn crate_of(name: &str) -> &str {
+    name.split("::").next().unwrap_or_default()
+}
+
+// A compact rendering of a signature's parameters and return type, e.g. `(a: u32) -> bool`
+pub fn format_signature(sig: &syn::Signature) -> String {
+    let params: Vec<String> = sig
+        .inputs
+        .iter()
+        .map(|input| match input {
+            syn::FnArg::Receiver(receiver) => tokens_to_string(receiver),
+            syn::FnArg::Typed(typed) => format!(
+                "{}: {}",
+                tokens_to_string(&typed.pat),
+                tokens_to_string(&typed.ty)
+            ),
         })
-    {
-        visitor.process_module(entry.path())?;
+        .collect();
+
+    match &sig.output {
+        syn::ReturnType::Default => format!("({})", params.join(", ")),
+        syn::ReturnType::Type(_, ty) => {
+            format!("({}) -> {}", params.join(", "), tokens_to_string(ty))
+        }
     }
+}
+
+// `extern "C" fn` and the like, or anything exported unmangled with `#[no_mangle]`
+// (written `#[unsafe(no_mangle)]` since edition 2024)
+pub fn is_ffi(sig: &syn::Signature, attrs: &[syn::Attribute]) -> bool {
+    sig.abi.is_some()
+        || attrs.iter().any(|attr| {
+            attr.path().is_ident("no_mangle")
+                || (attr.path().is_ident("unsafe")
+                    && tokens_to_string(&attr.meta).contains("no_mangle"))
+        })
+}
 
-    // Start analysis from main
-    visitor.process_function("main");
+// The first line of a `///` doc comment, cut short if it is long
+pub fn doc_summary(attrs: &[syn::Attribute]) -> Option<String> {
+    const MAX_CHARS: usize = 80;
+    let line = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value:
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(doc),
+                        ..
+                    }),
+                ..
+            }) => Some(doc.value()),
+            _ => None,
+        })
+        .flat_map(|doc| {
+            doc.lines()
+                .map(|line| line.trim().to_string())
+                .collect::<Vec<_>>()
+        })
+        .find(|line| !line.is_empty())?;
+
+    match line.chars().count() > MAX_CHARS {
+        true => Some(format!(
+            "{}…",
+            line.chars().take(MAX_CHARS - 1).collect::<String>()
+        )),
+        false => Some(line),
+    }
+}
+
+// `label` cut down to at most `max_chars` characters by leaving out whole path segments
+// from the middle, e.g. `mycrate::a::…::foo`, or characters from the middle when even
+// the first and last segments are too long
+pub fn truncate_label(label: &str, max_chars: usize) -> String {
+    let length = |s: &str| s.chars().count();
+    if length(label) <= max_chars {
+        return label.to_string();
+    }
+
+    let segments: Vec<&str> = label.split("::").collect();
+    let (leading, last) = segments.split_at(segments.len() - 1);
+    let mut kept = 0;
+    let mut used = length("…::") + length(last[0]);
+    while kept < leading.len() && used + length(leading[kept]) + 2 <= max_chars {
+        used += length(leading[kept]) + 2;
+        kept += 1;
+    }
+    match kept {
+        0 => {
+            let tail = max_chars.saturating_sub(1) / 2;
+            let head = max_chars.saturating_sub(1) - tail;
+            let chars: Vec<char> = label.chars().collect();
+            format!(
+                "{}…{}",
+                chars[..head].iter().collect::<String>(),
+                chars[chars.len() - tail..].iter().collect::<String>()
+            )
+        }
+        _ => format!("{}::…::{}", leading[..kept].join("::"), last[0]),
+    }
+}
+
+// The path of each attribute but doc comments, e.g. `deprecated` for `#[deprecated]`
+pub fn attribute_paths(attrs: &[syn::Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|attr| !attr.path().is_ident("doc"))
+        .map(|attr| tokens_to_string(attr.path()))
+        .collect()
+}
+
+// Whether `name` names the attribute `path`, in full or by its last segment, so that
+// `instrument` matches `tracing::instrument`
+pub fn attribute_matches(path: &str, name: &str) -> bool {
+    path == name || path.ends_with(&format!("::{}", name))
+}
+
+// `pub`, `pub(crate)` and so on, or `private` for inherited visibility
+pub fn format_visibility(vis: &syn::Visibility) -> String {
+    match vis {
+        syn::Visibility::Inherited => "private".to_string(),
+        _ => tokens_to_string(vis)
+            .replace(" (", "(")
+            .replace("( ", "(")
+            .replace(" )", ")"),
+    }
+}
+
+// The turbofish type arguments of a call path, e.g. `<u32>` for `convert::<u32>` or
+// `<String>` for `Vec::<String>::new`
+pub fn format_type_arguments(path: &syn::Path) -> Option<String> {
+    let arguments: Vec<String> = path
+        .segments
+        .iter()
+        .filter_map(|segment| match &segment.arguments {
+            syn::PathArguments::AngleBracketed(arguments) => Some(format!(
+                "<{}>",
+                arguments
+                    .args
+                    .iter()
+                    .map(tokens_to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+            _ => None,
+        })
+        .collect();
+
+    match arguments.is_empty() {
+        true => None,
+        false => Some(arguments.concat()),
+    }
+}
+
+// Token streams print with a space between every token; tighten the common cases
+fn tokens_to_string(tokens: &impl ToTokens) -> String {
+    tokens
+        .to_token_stream()
+        .to_string()
+        .replace(" :: ", "::")
+        .replace(" < ", "<")
+        .replace("< ", "<")
+        .replace(" >", ">")
+        .replace("& ", "&")
+        .replace(" ,", ",")
+}
 
-    Ok(visitor)
+// Number of source lines from the start of one span to the end of another
+pub fn line_count(start: proc_macro2::Span, end: proc_macro2::Span) -> usize {
+    (end.end().line + 1).saturating_sub(start.start().line)
 }
@@ -1,24 +1,197 @@
+use crate::{cache::Cache, utils};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::{
     collections::{HashMap, HashSet},
     error::Error,
     fs,
     path::Path,
 };
-use syn::{ImplItem, Item, ItemFn, parse_file, visit::Visit};
+use syn::{ImplItem, Item, parse_file, punctuated::Punctuated, spanned::Spanned, visit::Visit};
+
+// Where a function or method is defined
+#[derive(Clone, Debug)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: usize,
+}
+
+// A call as written, before it is resolved to the function it calls
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum CallSite {
+    // `f()`, `module::f()`, `Type::new()`
+    Path(Vec<String>),
+    // `<Foo as Bar>::baz()`, as the type's path with the method (`Foo::baz`) and the
+    // trait's (`Bar::baz`)
+    QualifiedPath(Vec<String>, Vec<String>),
+    // `convert::<u32>()`, with its type arguments
+    GenericPath(Vec<String>, String),
+    // `x.run()`
+    Method(String),
+    // `x.run()` where `x` is declared with a type, as `(type path, method name)`
+    TypedMethod(Vec<String>, String),
+    // `x.run()` where `x` is declared as a `dyn Trait` (behind a reference, `Box` and
+    // so on), as `(trait name, method name)`
+    DynMethod(String, String),
+    // `println!()`, `std::vec![]`
+    Macro(Vec<String>),
+}
+
+// The crates of the standard library, as a path's first segment
+const STD_CRATES: &[&str] = &["std", "core", "alloc"];
+
+// Standard library macros that are usually invoked without a path
+const STD_MACROS: &[&str] = &[
+    "assert",
+    "assert_eq",
+    "assert_ne",
+    "dbg",
+    "debug_assert",
+    "debug_assert_eq",
+    "debug_assert_ne",
+    "eprint",
+    "eprintln",
+    "format",
+    "matches",
+    "panic",
+    "print",
+    "println",
+    "todo",
+    "unimplemented",
+    "unreachable",
+    "vec",
+    "write",
+    "writeln",
+];
+
+// What a file contributes for one of its functions or methods
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Definition {
+    pub name: String,
+    pub is_method: bool,
+    pub calls: Vec<CallSite>,
+    // The line each call is made on, in the same order
+    pub call_lines: Vec<usize>,
+    pub signature: String,
+    pub lines_of_code: usize,
+    pub complexity: usize,
+    pub line: usize,
+    pub visibility: String,
+    pub ffi: bool,
+    pub is_async: bool,
+    pub is_unsafe: bool,
+    pub contains_unsafe: bool,
+    // For methods of `impl Trait for Type` blocks and default methods of `trait Trait`,
+    // the trait's name
+    pub implements: Option<String>,
+    // The initializer of a `const` or `static`, named like `crate::module::<const X>`
+    pub initializer: bool,
+    // The first line of its doc comment
+    pub doc: Option<String>,
+    // The paths of its attributes other than doc comments, e.g. `deprecated` or
+    // `tracing::instrument`
+    pub attributes: Vec<String>,
+}
+
+// Everything a file contributes. This is all that is kept of the syntax tree, so it
+// is also what the cache stores.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FileItems {
+    pub definitions: Vec<Definition>,
+    // `#[path = "..."] mod name;` declarations as `(name, path)`, the path relative to
+    // the declaring file's directory
+    pub path_modules: Vec<(String, String)>,
+}
 
 #[derive(Default)]
 pub struct FunctionCallVisitor {
+    pub crate_name: String,
     pub current_function: String,
     pub current_module: Vec<String>,
     pub function_calls: Vec<(String, String)>,
-    pub functions: HashMap<String, syn::ItemFn>,
-    pub struct_methods: HashMap<String, syn::ImplItemFn>,
-    pub impl_blocks: HashMap<String, Vec<syn::ImplItemFn>>,
+    // The line each of `function_calls` is made on, in the caller's file
+    pub call_lines: Vec<usize>,
+    // The line of each call a function makes, in the same order as its call sites
+    pub call_site_lines: HashMap<String, Vec<usize>>,
+    // The line of the call site being followed
+    current_call_line: usize,
+    // Every call site followed, by function and position among its calls, and those
+    // that resolved to at least one call. Macros aren't counted.
+    pub call_sites: HashSet<(String, usize)>,
+    pub resolved_call_sites: HashSet<(String, usize)>,
+    // Call sites into the standard library or a dependency that isn't analyzed, which
+    // are kept out of `call_sites` since they can't resolve to anything of ours
+    pub outside_call_sites: HashSet<(String, usize)>,
+    // The calls each function makes, keyed `crate::module::function`
+    pub functions: HashMap<String, Vec<CallSite>>,
+    // Every method, keyed `crate::module::Type::method`. This is the one map used to
+    // resolve methods, for path calls (`Type::new()`) and method calls (`x.run()`) alike,
+    // so a method's key is always its type's key followed by the method name.
+    pub struct_methods: HashMap<String, Vec<CallSite>>,
+    pub signatures: HashMap<String, String>,
+    pub lines_of_code: HashMap<String, usize>,
+    pub complexity: HashMap<String, usize>,
+    pub locations: HashMap<String, SourceLocation>,
+    // `pub`, `pub(crate)` and so on, or `private`
+    pub visibilities: HashMap<String, String>,
+    pub current_file: String,
+    // Functions callable across an FFI boundary: `extern "C" fn` or `#[no_mangle]`
+    pub ffi_functions: HashSet<String>,
+    pub async_functions: HashSet<String>,
+    // The first line of each function's doc comment, where it has one
+    pub docs: HashMap<String, String>,
+    // The attributes of each function that has any besides doc comments
+    pub attributes: HashMap<String, Vec<String>>,
+    // `unsafe fn`s
+    pub unsafe_functions: HashSet<String>,
+    // Functions with an `unsafe { }` block in their body
+    pub unsafe_block_functions: HashSet<String>,
+    // The methods implementing each trait, keyed by the trait's name, default methods
+    // included
+    pub trait_methods: HashMap<String, Vec<String>>,
+    // `const` and `static` initializers that call something, which are followed like
+    // entry points
+    pub initializers: Vec<String>,
+    // Every function calls were followed from, in the order they were followed
+    pub followed_entries: Vec<String>,
+    // Calls through a trait object to each method that might be behind it
+    pub possible_calls: HashSet<(String, String)>,
+    // Record calls into the standard library too, which are otherwise dropped
+    pub include_std: bool,
+    // How many calls deep to follow from an entry point, if not all the way
+    pub max_depth: Option<usize>,
+    // Only functions whose name matches are followed into; calls to the others are
+    // still recorded
+    pub only: Option<Regex>,
+    // Crates whose sources are loaded into this visitor
+    pub loaded_crates: HashSet<String>,
+    // Dependencies whose sources aren't loaded, so calls into them go nowhere
+    pub dependency_crates: HashSet<String>,
+    // Calls by path into one of `loaded_crates` that match none of its functions, as
+    // `(caller, path)`
+    pub unresolved_crate_calls: HashSet<(String, String)>,
+    // The standard library functions and macros called, e.g. `std::mem::swap` or
+    // `std::println!`
+    pub std_calls: HashSet<String>,
+    // The distinct type arguments each function was called with, where a call spells
+    // them out with a turbofish
+    pub instantiations: HashMap<String, HashSet<String>>,
+    // Module names of files declared with `#[path]`, keyed by canonical path, which
+    // take the place of the file's own name
+    pub module_paths: HashMap<String, String>,
     pub visited_files: HashSet<String>,
     pub current_call_stack: Vec<String>,
+    pub cache: Option<Cache>,
 }
 
 impl FunctionCallVisitor {
+    pub fn new(crate_name: &str) -> Self {
+        FunctionCallVisitor {
+            crate_name: crate_name.to_string(),
+            ..Default::default()
+        }
+    }
+
     fn get_qualified_name(&self, name: &str) -> String {
         match (name.contains("::"), self.current_module.is_empty()) {
             (true, _) => name.to_string(),
@@ -27,124 +200,621 @@ impl FunctionCallVisitor {
         }
     }
 
+    fn module_of(qualified_name: &str) -> Vec<String> {
+        match qualified_name.rsplit_once("::") {
+            Some((module, _)) => module.split("::").map(String::from).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    // Try the path relative to the current module, then the crate root, then as an
+    // absolute path (which is how calls into dependency crates resolve)
+    fn resolve_call_path(&self, segments: &[String]) -> Option<String> {
+        let crate_root = self.current_module.first().cloned().unwrap_or_default();
+        let path = segments.join("::");
+
+        let candidates = match segments.first().map(String::as_str) {
+            Some("crate") => vec![format!("{}::{}", crate_root, segments[1..].join("::"))],
+            Some("self") => vec![format!(
+                "{}::{}",
+                self.current_module.join("::"),
+                segments[1..].join("::")
+            )],
+            Some("super") => {
+                let parent = &self.current_module[..self.current_module.len().saturating_sub(1)];
+                vec![format!(
+                    "{}::{}",
+                    parent.join("::"),
+                    segments[1..].join("::")
+                )]
+            }
+            _ => {
+                let mut candidates: Vec<String> = self
+                    .enclosing_scopes()
+                    .into_iter()
+                    .map(|scope| format!("{}::{}", scope, path))
+                    .collect();
+                candidates.push(format!("{}::{}", crate_root, path));
+                candidates.push(path);
+                candidates
+            }
+        };
+
+        candidates.into_iter().find(|candidate| {
+            self.functions.contains_key(candidate) || self.struct_methods.contains_key(candidate)
+        })
+    }
+
+    // Where a bare path may be defined, innermost first: functions nested in the current
+    // one, then, for a nested function, the bodies of the functions around it, then the
+    // module
+    fn enclosing_scopes(&self) -> Vec<String> {
+        let mut scopes = vec![self.current_function.clone()];
+        let mut scope = self.current_module.clone();
+        loop {
+            let name = scope.join("::");
+            let is_function = self.functions.contains_key(&name);
+            let is_method = self.struct_methods.contains_key(&name);
+            scopes.push(name);
+            match (is_function, is_method) {
+                (true, _) => {
+                    scope.pop();
+                }
+                // The method's type isn't a scope of its own
+                (false, true) => scope.truncate(scope.len().saturating_sub(2)),
+                (false, false) => return scopes,
+            }
+        }
+    }
+
+    // The receiver's type isn't known, so pick the first type (by key) with a method
+    // of this name. Named types go before keys like `<slice u8>`, which would otherwise
+    // sort first.
+    fn resolve_method_call(&self, method_name: &str) -> Option<String> {
+        self.struct_methods
+            .keys()
+            .filter_map(|key| key.rsplit_once("::"))
+            .filter(|(_, name)| *name == method_name)
+            .map(|(type_key, _)| type_key)
+            .min_by_key(|type_key| {
+                let synthetic = type_key
+                    .rsplit("::")
+                    .next()
+                    .is_some_and(|name| name.starts_with('<'));
+                (synthetic, *type_key)
+            })
+            .map(|type_key| type_key.to_string())
+    }
+
+    // Whether to stop at a function whose call has just been recorded rather than follow
+    // what it calls, because it is too deep or outside `only`. Entry points are always
+    // followed.
+    fn stops_at(&self, name: &str) -> bool {
+        let too_deep = self
+            .max_depth
+            .is_some_and(|max_depth| self.current_call_stack.len() >= max_depth);
+        let excluded = !self.current_call_stack.is_empty()
+            && self.only.as_ref().is_some_and(|only| !only.is_match(name));
+        too_deep || excluded
+    }
+
     pub fn process_function(&mut self, name: &str) {
         let qualified_name = self.get_qualified_name(name);
 
-        if self.current_call_stack.contains(&qualified_name) {
-            return; // Prevent infinite recursion
+        // Prevent infinite recursion. Callers record the call before getting here, so a
+        // recursive call still shows up as an edge (a self-loop for direct recursion).
+        if self.current_call_stack.contains(&qualified_name) || self.stops_at(&qualified_name) {
+            return;
         }
 
         self.current_call_stack.push(qualified_name.clone());
 
         match self.functions.get(&qualified_name).cloned() {
-            Some(func) => {
+            Some(calls) => {
                 let old_function = self.current_function.clone();
+                let old_module =
+                    std::mem::replace(&mut self.current_module, Self::module_of(&qualified_name));
+                let old_line = self.current_call_line;
                 self.current_function = qualified_name;
-                syn::visit::visit_item_fn(self, &func);
+                self.follow_calls(&calls);
                 self.current_function = old_function;
+                self.current_module = old_module;
+                self.current_call_line = old_line;
             }
             None => (),
         }
         self.current_call_stack.pop();
     }
 
-    fn process_method(&mut self, type_name: &str, method_name: &str) {
+    pub fn process_method(&mut self, type_name: &str, method_name: &str) {
         let qualified_method = format!("{}::{}", type_name, method_name);
-        if self.current_call_stack.contains(&qualified_method) {
+        // As in `process_function`, the recursive call has already been recorded
+        if self.current_call_stack.contains(&qualified_method) || self.stops_at(&qualified_method) {
             return;
         }
         self.current_call_stack.push(qualified_method.clone());
-        let method_to_process = self.impl_blocks.get(type_name).and_then(|impls| {
-            impls
-                .iter()
-                .find(|method| method.sig.ident.to_string() == method_name)
-                .cloned()
-        });
+        let method_to_process = self.struct_methods.get(&qualified_method).cloned();
 
         match method_to_process {
-            Some(method) => {
+            Some(calls) => {
                 let old_function = self.current_function.clone();
+                let old_module =
+                    std::mem::replace(&mut self.current_module, Self::module_of(type_name));
+                let old_line = self.current_call_line;
                 self.current_function = qualified_method;
-                syn::visit::visit_impl_item_fn(self, &method);
+                self.follow_calls(&calls);
                 self.current_function = old_function;
+                self.current_module = old_module;
+                self.current_call_line = old_line;
             }
             None => (),
         }
         self.current_call_stack.pop();
     }
 
-    fn process_impl_block(&mut self, impl_block: &syn::ItemImpl) -> Result<(), Box<dyn Error>> {
-        let type_name = match &*impl_block.self_ty {
-            syn::Type::Path(type_path) => {
-                type_path.path.segments.last().map(|s| s.ident.to_string())
+    // Records each call the current function makes, in source order, following each
+    // callee as soon as its call is recorded
+    fn follow_calls(&mut self, calls: &[CallSite]) {
+        let lines = self
+            .call_site_lines
+            .get(&self.current_function)
+            .cloned()
+            .unwrap_or_default();
+        for (i, call) in calls.iter().enumerate() {
+            self.current_call_line = lines.get(i).copied().unwrap_or_default();
+            let call_site = (self.current_function.clone(), i);
+            let calls_before = self.function_calls.len();
+            let outside = match call {
+                CallSite::Path(segments) | CallSite::GenericPath(segments, _) => {
+                    self.is_outside_path(segments) && self.resolve_call_path(segments).is_none()
+                }
+                _ => false,
+            };
+            match call {
+                CallSite::Path(segments) => {
+                    self.follow_path_call(segments);
+                }
+                // The type's own method if it has one, otherwise the trait's default
+                CallSite::QualifiedPath(type_path, trait_path) => {
+                    match self.resolve_call_path(type_path) {
+                        Some(_) => self.follow_path_call(type_path),
+                        None => self.follow_path_call(trait_path),
+                    };
+                }
+                CallSite::GenericPath(segments, type_arguments) => {
+                    match self.follow_path_call(segments) {
+                        Some(callee) => {
+                            self.instantiations
+                                .entry(callee)
+                                .or_default()
+                                .insert(type_arguments.clone());
+                        }
+                        None => (),
+                    }
+                }
+                CallSite::Macro(segments) => match segments.as_slice() {
+                    [first, ..] if STD_CRATES.contains(&first.as_str()) => {
+                        self.record_std_call(format!("{}!", segments.join("::")))
+                    }
+                    [name] if STD_MACROS.contains(&name.as_str()) => {
+                        self.record_std_call(format!("std::{}!", name))
+                    }
+                    _ => (),
+                },
+                CallSite::Method(method_name) => self.follow_method_call(method_name),
+                // The declared type's method if it is one of ours, otherwise whichever
+                // type has a method of this name
+                CallSite::TypedMethod(type_path, method_name) => {
+                    let mut segments = type_path.clone();
+                    segments.push(method_name.clone());
+                    match self.resolve_call_path(&segments) {
+                        Some(_) => {
+                            self.follow_path_call(&segments);
+                        }
+                        None => self.follow_method_call(method_name),
+                    }
+                }
+                CallSite::DynMethod(trait_name, method_name) => {
+                    let implementations = self.resolve_dyn_method_call(trait_name, method_name);
+                    match implementations.is_empty() {
+                        // The trait isn't one of ours, so fall back to a plain method call
+                        true => self.follow_method_call(method_name),
+                        false => {
+                            let caller = self.get_qualified_name(&self.current_function);
+                            for qualified_method in implementations {
+                                self.record_call(caller.clone(), qualified_method.clone());
+                                self.possible_calls
+                                    .insert((caller.clone(), qualified_method.clone()));
+                                match qualified_method.rsplit_once("::") {
+                                    Some((type_name, method_name)) => {
+                                        self.process_method(type_name, method_name)
+                                    }
+                                    None => (),
+                                }
+                            }
+                        }
+                    }
+                }
             }
-            _ => None,
+
+            match (call, outside, self.function_calls.len() > calls_before) {
+                (CallSite::Macro(_), _, _) => (),
+                (_, true, _) => {
+                    self.outside_call_sites.insert(call_site);
+                }
+                (_, false, true) => {
+                    self.call_sites.insert(call_site.clone());
+                    self.resolved_call_sites.insert(call_site);
+                }
+                (_, false, false) => {
+                    self.call_sites.insert(call_site);
+                }
+            }
+        }
+    }
+
+    // Whether a path starts with `std`, `core` or `alloc`, or names a function of a
+    // dependency that isn't analyzed
+    fn is_outside_path(&self, segments: &[String]) -> bool {
+        match segments.first() {
+            Some(first) if STD_CRATES.contains(&first.as_str()) => true,
+            Some(first) => segments.len() > 1 && self.dependency_crates.contains(first),
+            None => false,
+        }
+    }
+
+    // Returns the function called, if it is one of ours
+    fn follow_path_call(&mut self, segments: &[String]) -> Option<String> {
+        match self.resolve_call_path(segments) {
+            Some(qualified_callee) => {
+                let caller = self.get_qualified_name(&self.current_function);
+                self.record_call(caller, qualified_callee.clone());
+
+                match qualified_callee.rsplit_once("::") {
+                    Some(parts) if !self.functions.contains_key(&qualified_callee) => {
+                        self.process_method(parts.0, parts.1);
+                    }
+                    _ => self.process_function(&qualified_callee),
+                }
+                Some(qualified_callee)
+            }
+            None => {
+                match segments.first() {
+                    Some(first) if STD_CRATES.contains(&first.as_str()) => {
+                        self.record_std_call(segments.join("::"))
+                    }
+                    Some(first) if segments.len() > 1 && self.loaded_crates.contains(first) => {
+                        let caller = self.get_qualified_name(&self.current_function);
+                        self.unresolved_crate_calls
+                            .insert((caller, segments.join("::")));
+                    }
+                    _ => (),
+                }
+                None
+            }
+        }
+    }
+
+    fn record_call(&mut self, caller: String, callee: String) {
+        self.function_calls.push((caller, callee));
+        self.call_lines.push(self.current_call_line);
+    }
+
+    // Standard library calls end the chain, as their bodies aren't analyzed
+    fn record_std_call(&mut self, callee: String) {
+        if !self.include_std {
+            return;
+        }
+        let caller = self.get_qualified_name(&self.current_function);
+        self.record_call(caller, callee.clone());
+        self.std_calls.insert(callee);
+    }
+
+    fn follow_method_call(&mut self, method_name: &str) {
+        match self.resolve_method_call(method_name) {
+            Some(struct_name) => {
+                let qualified_method = format!("{}::{}", struct_name, method_name);
+                let caller = self.get_qualified_name(&self.current_function);
+                self.record_call(caller, qualified_method.clone());
+                self.process_method(&struct_name, method_name);
+            }
+            None => (),
+        }
+    }
+
+    // Every implementation of the trait's method, since any of them could be behind a
+    // trait object
+    fn resolve_dyn_method_call(&self, trait_name: &str, method_name: &str) -> Vec<String> {
+        let mut implementations: Vec<String> = self
+            .trait_methods
+            .get(trait_name)
+            .into_iter()
+            .flatten()
+            .filter(|name| name.rsplit("::").next() == Some(method_name))
+            .cloned()
+            .collect();
+        implementations.sort();
+        implementations.dedup();
+        implementations
+    }
+
+    // The function and any functions defined in its body
+    fn function_definitions(&self, func: &syn::ItemFn) -> Vec<Definition> {
+        let definition = self.function_definition(func);
+        let mut definitions = self.nested_definitions(&definition.name, &func.block);
+        definitions.push(definition);
+        definitions
+    }
+
+    // Functions defined inside a body, keyed under the function or method that defines
+    // them, e.g. `crate::module::outer::inner`, and so on for functions nested deeper
+    fn nested_definitions(&self, outer: &str, block: &syn::Block) -> Vec<Definition> {
+        let mut finder = NestedFnFinder::default();
+        finder.visit_block(block);
+
+        let mut definitions = Vec::new();
+        for func in finder.functions {
+            let mut definition = self.function_definition(func);
+            definition.name = format!("{}::{}", outer, func.sig.ident);
+            definitions.extend(self.nested_definitions(&definition.name, &func.block));
+            definitions.push(definition);
+        }
+        definitions
+    }
+
+    fn function_definition(&self, func: &syn::ItemFn) -> Definition {
+        let name = func.sig.ident.to_string();
+        let (calls, call_lines) =
+            CallSiteCollector::collect(|collector| collector.visit_item_fn(func));
+        Definition {
+            name: self.get_qualified_name(&name),
+            is_method: false,
+            calls,
+            call_lines,
+            signature: utils::format_signature(&func.sig),
+            lines_of_code: utils::line_count(func.sig.span(), func.block.span()),
+            complexity: cyclomatic_complexity(&func.block),
+            line: func.sig.span().start().line,
+            visibility: utils::format_visibility(&func.vis),
+            ffi: utils::is_ffi(&func.sig, &func.attrs),
+            is_async: func.sig.asyncness.is_some(),
+            is_unsafe: func.sig.unsafety.is_some(),
+            contains_unsafe: contains_unsafe_block(&func.block),
+            implements: None,
+            initializer: false,
+            doc: utils::doc_summary(&func.attrs),
+            attributes: utils::attribute_paths(&func.attrs),
+        }
+    }
+
+    fn method_definition(
+        &self,
+        qualified_name: String,
+        method: &syn::ImplItemFn,
+        implements: Option<String>,
+    ) -> Definition {
+        let (calls, call_lines) =
+            CallSiteCollector::collect(|collector| collector.visit_impl_item_fn(method));
+        Definition {
+            name: qualified_name,
+            is_method: true,
+            calls,
+            call_lines,
+            signature: utils::format_signature(&method.sig),
+            lines_of_code: utils::line_count(method.sig.span(), method.block.span()),
+            complexity: cyclomatic_complexity(&method.block),
+            line: method.sig.span().start().line,
+            visibility: utils::format_visibility(&method.vis),
+            ffi: utils::is_ffi(&method.sig, &method.attrs),
+            is_async: method.sig.asyncness.is_some(),
+            is_unsafe: method.sig.unsafety.is_some(),
+            contains_unsafe: contains_unsafe_block(&method.block),
+            implements,
+            initializer: false,
+            doc: utils::doc_summary(&method.attrs),
+            attributes: utils::attribute_paths(&method.attrs),
+        }
+    }
+
+    // A trait's default method, keyed like a method of a type named after the trait
+    fn trait_method_definition(
+        &self,
+        trait_key: &str,
+        trait_name: &str,
+        vis: &syn::Visibility,
+        method: &syn::TraitItemFn,
+    ) -> Option<Definition> {
+        let block = method.default.as_ref()?;
+        let (calls, call_lines) =
+            CallSiteCollector::collect(|collector| collector.visit_trait_item_fn(method));
+        Some(Definition {
+            name: format!("{}::{}", trait_key, method.sig.ident),
+            is_method: true,
+            calls,
+            call_lines,
+            signature: utils::format_signature(&method.sig),
+            lines_of_code: utils::line_count(method.sig.span(), block.span()),
+            complexity: cyclomatic_complexity(block),
+            line: method.sig.span().start().line,
+            visibility: utils::format_visibility(vis),
+            ffi: utils::is_ffi(&method.sig, &method.attrs),
+            is_async: method.sig.asyncness.is_some(),
+            is_unsafe: method.sig.unsafety.is_some(),
+            contains_unsafe: contains_unsafe_block(block),
+            implements: Some(trait_name.to_string()),
+            initializer: false,
+            doc: utils::doc_summary(&method.attrs),
+            attributes: utils::attribute_paths(&method.attrs),
+        })
+    }
+
+    // `const X: T = compute();` and `static Y: T = init();`, if the initializer calls
+    // anything
+    fn initializer_definition(
+        &self,
+        kind: &str,
+        ident: &syn::Ident,
+        vis: &syn::Visibility,
+        expr: &syn::Expr,
+    ) -> Option<Definition> {
+        let (calls, call_lines) =
+            CallSiteCollector::collect(|collector| collector.visit_expr(expr));
+        if calls.is_empty() {
+            return None;
+        }
+        Some(Definition {
+            name: self.get_qualified_name(&format!("<{} {}>", kind, ident)),
+            is_method: false,
+            calls,
+            call_lines,
+            signature: String::new(),
+            lines_of_code: utils::line_count(ident.span(), expr.span()),
+            complexity: 1,
+            line: ident.span().start().line,
+            visibility: utils::format_visibility(vis),
+            ffi: false,
+            is_async: false,
+            is_unsafe: false,
+            contains_unsafe: contains_unsafe_expr(expr),
+            implements: None,
+            initializer: true,
+            doc: None,
+            attributes: Vec::new(),
+        })
+    }
+
+    fn trait_definitions(&self, item_trait: &syn::ItemTrait) -> Vec<Definition> {
+        let trait_name = item_trait.ident.to_string();
+        let trait_key = self.get_qualified_name(&trait_name);
+
+        item_trait
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                syn::TraitItem::Fn(method) => {
+                    self.trait_method_definition(&trait_key, &trait_name, &item_trait.vis, method)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn add_definition(&mut self, definition: Definition) {
+        let name = definition.name;
+        if definition.initializer {
+            self.initializers.push(name.clone());
+        }
+        match definition.implements {
+            Some(trait_name) => self
+                .trait_methods
+                .entry(trait_name)
+                .or_default()
+                .push(name.clone()),
+            None => (),
+        }
+        self.signatures.insert(name.clone(), definition.signature);
+        self.lines_of_code
+            .insert(name.clone(), definition.lines_of_code);
+        self.complexity.insert(name.clone(), definition.complexity);
+        let location = SourceLocation {
+            file: self.current_file.clone(),
+            line: definition.line,
+        };
+        self.locations.insert(name.clone(), location);
+        self.visibilities
+            .insert(name.clone(), definition.visibility);
+        if definition.ffi {
+            self.ffi_functions.insert(name.clone());
+        }
+        if definition.is_async {
+            self.async_functions.insert(name.clone());
+        }
+        match definition.doc {
+            Some(doc) => {
+                self.docs.insert(name.clone(), doc);
+            }
+            None => (),
+        }
+        if !definition.attributes.is_empty() {
+            self.attributes.insert(name.clone(), definition.attributes);
+        }
+        if definition.is_unsafe {
+            self.unsafe_functions.insert(name.clone());
+        }
+        if definition.contains_unsafe {
+            self.unsafe_block_functions.insert(name.clone());
+        }
+        self.call_site_lines
+            .insert(name.clone(), definition.call_lines);
+        match definition.is_method {
+            true => self.struct_methods.insert(name, definition.calls),
+            false => self.functions.insert(name, definition.calls),
         };
+    }
 
+    fn impl_block_definitions(&self, impl_block: &syn::ItemImpl) -> Vec<Definition> {
+        let type_name = impl_type_key(&impl_block.self_ty);
+
+        // `impl Trait for Type`
+        let trait_name = impl_block
+            .trait_
+            .as_ref()
+            .and_then(|(_, path, _)| path.segments.last())
+            .map(|segment| segment.ident.to_string());
+
+        let mut definitions = Vec::new();
         match type_name {
             Some(type_name) => {
-                let mut methods = Vec::new();
+                let type_key = self.get_qualified_name(&type_name);
 
                 for item in &impl_block.items {
                     match item {
                         ImplItem::Fn(method) => {
                             let method_name = method.sig.ident.to_string();
-                            let qualified_name = match self.current_module.is_empty() {
-                                true => format!("{}::{}", type_name, method_name),
-                                false => format!(
-                                    "{}::{}::{}",
-                                    self.current_module.join("::"),
-                                    type_name,
-                                    method_name
-                                ),
-                            };
-                            self.struct_methods.insert(qualified_name, method.clone());
-                            methods.push(method.clone());
+                            let qualified_name = format!("{}::{}", type_key, method_name);
+                            definitions
+                                .extend(self.nested_definitions(&qualified_name, &method.block));
+                            definitions.push(self.method_definition(
+                                qualified_name,
+                                method,
+                                trait_name.clone(),
+                            ));
                         }
                         _ => (),
                     }
                 }
-
-                self.impl_blocks.insert(type_name, methods);
             }
             None => (),
         }
 
-        Ok(())
+        definitions
     }
 
-    pub fn process_module(&mut self, module_path: &Path) -> Result<(), Box<dyn Error>> {
-        let canon_path = module_path.canonicalize()?;
-        let path_str = canon_path.to_string_lossy().to_string();
-
-        if self.visited_files.contains(&path_str) {
-            return Ok(());
-        }
-        self.visited_files.insert(path_str);
-
-        let content = fs::read_to_string(module_path)?;
-        let syntax = parse_file(&content)?;
-
-        let module_name = module_path
-            .file_stem()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .into_owned();
-
-        if module_name != "main" && module_name != "lib" {
-            self.current_module.push(module_name);
-        }
+    // Everything `process_module` needs from a parsed file, with names qualified by the
+    // current module
+    fn file_items(&mut self, syntax: syn::File) -> FileItems {
+        let mut definitions = Vec::new();
+        let mut path_modules = Vec::new();
 
         for item in syntax.items {
             match item {
-                Item::Fn(func) => {
-                    let name = func.sig.ident.to_string();
-                    let qualified_name = self.get_qualified_name(&name);
-                    self.functions.insert(qualified_name, func);
-                }
+                Item::Fn(func) => definitions.extend(self.function_definitions(&func)),
                 Item::Impl(impl_block) => {
-                    self.process_impl_block(&impl_block)?;
+                    definitions.extend(self.impl_block_definitions(&impl_block));
                 }
+                Item::Trait(item_trait) => definitions.extend(self.trait_definitions(&item_trait)),
+                Item::Const(item) => definitions.extend(self.initializer_definition(
+                    "const",
+                    &item.ident,
+                    &item.vis,
+                    &item.expr,
+                )),
+                Item::Static(item) => definitions.extend(self.initializer_definition(
+                    "static",
+                    &item.ident,
+                    &item.vis,
+                    &item.expr,
+                )),
                 Item::Mod(module) => match module.content {
                     Some((_, items)) => {
                         let mod_name = module.ident.to_string();
@@ -153,12 +823,29 @@ impl FunctionCallVisitor {
                         for item in items {
                             match item {
                                 Item::Fn(func) => {
-                                    let name = func.sig.ident.to_string();
-                                    let qualified_name = self.get_qualified_name(&name);
-                                    self.functions.insert(qualified_name, func);
+                                    definitions.extend(self.function_definitions(&func))
                                 }
                                 Item::Impl(impl_block) => {
-                                    self.process_impl_block(&impl_block)?;
+                                    definitions.extend(self.impl_block_definitions(&impl_block));
+                                }
+                                Item::Trait(item_trait) => {
+                                    definitions.extend(self.trait_definitions(&item_trait))
+                                }
+                                Item::Const(item) => {
+                                    definitions.extend(self.initializer_definition(
+                                        "const",
+                                        &item.ident,
+                                        &item.vis,
+                                        &item.expr,
+                                    ))
+                                }
+                                Item::Static(item) => {
+                                    definitions.extend(self.initializer_definition(
+                                        "static",
+                                        &item.ident,
+                                        &item.vis,
+                                        &item.expr,
+                                    ))
                                 }
                                 _ => (),
                             }
@@ -166,71 +853,284 @@ impl FunctionCallVisitor {
 
                         self.current_module.pop();
                     }
-                    None => (),
+                    None => match path_attribute(&module.attrs) {
+                        Some(path) => path_modules.push((module.ident.to_string(), path)),
+                        None => (),
+                    },
                 },
                 _ => (),
             }
         }
 
-        if !self.current_module.is_empty() {
+        FileItems {
+            definitions,
+            path_modules,
+        }
+    }
+
+    pub fn process_module(&mut self, module_path: &Path) -> Result<(), Box<dyn Error>> {
+        let canon_path = module_path.canonicalize()?;
+        let path_str = canon_path.to_string_lossy().to_string();
+
+        if self.visited_files.contains(&path_str) {
+            return Ok(());
+        }
+        self.visited_files.insert(path_str.clone());
+
+        // Skip files that aren't UTF-8 (legacy latin-1 sources, say) rather than give up
+        // on the whole run
+        let content = match String::from_utf8(fs::read(module_path)?) {
+            Ok(content) => content,
+            Err(_) => {
+                log::warn!("Skipping '{}': not valid UTF-8", module_path.display());
+                return Ok(());
+            }
+        };
+        let content = content.strip_prefix('\u{feff}').unwrap_or(&content);
+        self.current_file = module_path.to_string_lossy().into_owned();
+
+        let module_name = match self.module_paths.get(&path_str) {
+            Some(module_name) => module_name.clone(),
+            None => match utils::binary_directory(&canon_path) {
+                Some(binary) => binary,
+                None => module_path
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned(),
+            },
+        };
+
+        let is_crate_root = module_name == "main" || module_name == "lib";
+        if !is_crate_root {
+            self.current_module.push(module_name);
+        }
+
+        // The same file is read under different module paths when it belongs to
+        // several targets, so both go into the cache key
+        let cache_key = format!("{}#{}", path_str, self.current_module.join("::"));
+        let cached = match &self.cache {
+            Some(cache) => cache.load(&cache_key, content),
+            None => None,
+        };
+        let items = match cached {
+            Some(items) => items,
+            None => {
+                let items = self.file_items(parse_file(content)?);
+                match &self.cache {
+                    Some(cache) => cache.store(&cache_key, content, &items),
+                    None => (),
+                }
+                items
+            }
+        };
+        for definition in items.definitions {
+            self.add_definition(definition);
+        }
+
+        if !is_crate_root {
             self.current_module.pop();
         }
 
+        // Files named by `#[path]` may lie anywhere, so they are read right away under
+        // the module name they were declared with rather than left to the directory walk
+        let dir = module_path.parent().unwrap_or(Path::new("."));
+        for (name, path) in items.path_modules {
+            let file = dir.join(&path);
+            match file.canonicalize() {
+                Ok(canonical) => {
+                    self.module_paths
+                        .insert(canonical.to_string_lossy().into_owned(), name);
+                    self.process_module(&file)?;
+                }
+                Err(_) => log::warn!(
+                    "Module file '{}' declared with #[path] in '{}' not found",
+                    path,
+                    module_path.display()
+                ),
+            }
+        }
+
         Ok(())
     }
-}
-impl<'ast> Visit<'ast> for FunctionCallVisitor {
-    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
-        match &*call.func {
-            syn::Expr::Path(path) => match path.path.segments.last().map(|s| s.ident.to_string()) {
-                Some(callee) => {
-                    let qualified_callee = match path.path.segments.len() > 1 {
-                        true => path
-                            .path
-                            .segments
-                            .iter()
-                            .map(|s| s.ident.to_string())
-                            .collect::<Vec<_>>()
-                            .join("::"),
-                        false => self.get_qualified_name(&callee),
-                    };
 
-                    if self.functions.contains_key(&qualified_callee)
-                        || self.struct_methods.contains_key(&qualified_callee)
-                    {
-                        let caller = self.get_qualified_name(&self.current_function);
-                        self.function_calls
-                            .push((caller.clone(), qualified_callee.clone()));
+    // Records the module names a file gives to its `#[path]` modules, so their files are
+    // named the same however early the directory walk reaches them
+    pub fn declare_path_modules(&mut self, module_path: &Path) {
+        let content = match fs::read_to_string(module_path) {
+            Ok(content) if content.contains("#[path") => content,
+            _ => return,
+        };
+        let syntax = match parse_file(content.strip_prefix('\u{feff}').unwrap_or(&content)) {
+            Ok(syntax) => syntax,
+            Err(_) => return,
+        };
 
-                        match qualified_callee.rsplit_once("::") {
-                            Some(parts) if !self.functions.contains_key(&qualified_callee) => {
-                                self.process_method(parts.0, parts.1);
-                            }
-                            _ => self.process_function(&qualified_callee),
+        let dir = module_path.parent().unwrap_or(Path::new("."));
+        for item in syntax.items {
+            match item {
+                Item::Mod(module) if module.content.is_none() => {
+                    match path_attribute(&module.attrs)
+                        .and_then(|path| dir.join(path).canonicalize().ok())
+                    {
+                        Some(canonical) => {
+                            self.module_paths.insert(
+                                canonical.to_string_lossy().into_owned(),
+                                module.ident.to_string(),
+                            );
                         }
+                        None => (),
                     }
                 }
-                None => (),
-            },
-            syn::Expr::MethodCall(method_call) => {
-                let method_name = method_call.method.to_string();
+                _ => (),
+            }
+        }
+    }
+}
 
-                match self
-                    .impl_blocks
+// The functions defined directly in a body, not those nested in them in turn
+#[derive(Default)]
+struct NestedFnFinder<'ast> {
+    functions: Vec<&'ast syn::ItemFn>,
+}
+
+impl<'ast> Visit<'ast> for NestedFnFinder<'ast> {
+    fn visit_item_fn(&mut self, func: &'ast syn::ItemFn) {
+        self.functions.push(func);
+    }
+}
+
+// The value of a `#[path = "..."]` attribute
+fn path_attribute(attrs: &[syn::Attribute]) -> Option<String> {
+    attrs.iter().find_map(|attr| match &attr.meta {
+        syn::Meta::NameValue(name_value) if name_value.path.is_ident("path") => {
+            match &name_value.value {
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(path),
+                    ..
+                }) => Some(path.value()),
+                _ => None,
+            }
+        }
+        _ => None,
+    })
+}
+
+// A call and the line it is made on
+type LocatedCall = (CallSite, usize);
+
+// The calls made in a function body, in the order the traversal follows them
+#[derive(Default)]
+struct CallSiteCollector {
+    calls: Vec<LocatedCall>,
+    // Parameters and `let` bindings declared as trait objects, and their trait
+    trait_objects: HashMap<String, String>,
+    // Parameters and `let` bindings declared with a named type, and its path
+    typed_bindings: HashMap<String, Vec<String>>,
+    // Closures bound with `let`, with the calls they make and whether they were invoked
+    closures: Vec<(String, Vec<LocatedCall>, bool)>,
+}
+
+impl CallSiteCollector {
+    // The calls, and in the same order the lines they are made on
+    fn collect(visit: impl FnOnce(&mut CallSiteCollector)) -> (Vec<CallSite>, Vec<usize>) {
+        let mut collector = CallSiteCollector::default();
+        visit(&mut collector);
+        collector.finish()
+    }
+
+    // A closure that is never invoked here is probably passed on to be called
+    // elsewhere, so its calls still count, after everything else
+    fn finish_closures(&mut self) {
+        for (_, calls, invoked) in self.closures.drain(..) {
+            if !invoked {
+                self.calls.extend(calls);
+            }
+        }
+    }
+
+    fn finish(mut self) -> (Vec<CallSite>, Vec<usize>) {
+        self.finish_closures();
+        self.calls.into_iter().unzip()
+    }
+
+    fn push(&mut self, call: CallSite, span: proc_macro2::Span) {
+        self.calls.push((call, span.start().line));
+    }
+
+    // The innermost closure bound to `name`, if any
+    fn closure_mut(&mut self, name: &str) -> Option<&mut (String, Vec<LocatedCall>, bool)> {
+        self.closures
+            .iter_mut()
+            .rev()
+            .find(|(closure, _, _)| closure == name)
+    }
+}
+
+impl<'ast> Visit<'ast> for CallSiteCollector {
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        let closure_name = match &*call.func {
+            syn::Expr::Path(path) => path.path.get_ident().map(|ident| ident.to_string()),
+            _ => None,
+        };
+        // `f()` for a closure `f` makes the closure's calls
+        match closure_name.and_then(|name| self.closure_mut(&name)) {
+            Some((_, calls, invoked)) => {
+                *invoked = true;
+                let calls = calls.clone();
+                self.calls.extend(calls);
+                for arg in &call.args {
+                    self.visit_expr(arg);
+                }
+                return;
+            }
+            None => (),
+        }
+
+        match &*call.func {
+            syn::Expr::Path(syn::ExprPath {
+                qself: Some(qself),
+                path,
+                ..
+            }) => match &*qself.ty {
+                syn::Type::Path(self_type) => {
+                    let method = path.segments.iter().skip(qself.position);
+                    let type_path = self_type.path.segments.iter().chain(method);
+                    // `<Foo>::baz()` names no trait
+                    let trait_path = match qself.position {
+                        0 => Vec::new(),
+                        _ => path.segments.iter().map(|s| s.ident.to_string()).collect(),
+                    };
+                    self.push(
+                        CallSite::QualifiedPath(
+                            type_path.map(|s| s.ident.to_string()).collect(),
+                            trait_path,
+                        ),
+                        call.span(),
+                    );
+                }
+                _ => (),
+            },
+            syn::Expr::Path(path) => {
+                let segments: Vec<String> = path
+                    .path
+                    .segments
                     .iter()
-                    .find(|(_, methods)| methods.iter().any(|m| m.sig.ident == method_call.method))
-                    .map(|(struct_name, _)| struct_name.clone())
-                {
-                    Some(struct_name) => {
-                        let qualified_method = format!("{}::{}", struct_name, method_name);
-                        let caller = self.get_qualified_name(&self.current_function);
-                        self.function_calls
-                            .push((caller.clone(), qualified_method.clone()));
-                        self.process_method(&struct_name, &method_name);
+                    .map(|s| s.ident.to_string())
+                    .collect();
+                match utils::format_type_arguments(&path.path) {
+                    Some(type_arguments) => {
+                        self.push(CallSite::GenericPath(segments, type_arguments), call.span())
                     }
-                    None => (),
+                    None => self.push(CallSite::Path(segments), call.span()),
                 }
             }
+            syn::Expr::MethodCall(method_call) => {
+                self.push(
+                    CallSite::Method(method_call.method.to_string()),
+                    call.span(),
+                );
+            }
             _ => (),
         }
         syn::visit::visit_expr_call(self, call);
@@ -238,23 +1138,411 @@ impl<'ast> Visit<'ast> for FunctionCallVisitor {
 
     fn visit_expr_method_call(&mut self, method_call: &'ast syn::ExprMethodCall) {
         let method_name = method_call.method.to_string();
+        let trait_name = match &*method_call.receiver {
+            syn::Expr::Path(path) => path
+                .path
+                .get_ident()
+                .and_then(|ident| self.trait_objects.get(&ident.to_string())),
+            _ => None,
+        };
+        let type_path = match &*method_call.receiver {
+            syn::Expr::Path(path) => path
+                .path
+                .get_ident()
+                .and_then(|ident| self.typed_bindings.get(&ident.to_string())),
+            _ => None,
+        };
+        let call = match (trait_name, type_path) {
+            (Some(trait_name), _) => CallSite::DynMethod(trait_name.clone(), method_name),
+            (None, Some(type_path)) => CallSite::TypedMethod(type_path.clone(), method_name),
+            (None, None) => CallSite::Method(method_name),
+        };
+        self.push(call, method_call.method.span());
+        syn::visit::visit_expr_method_call(self, method_call);
+    }
 
-        match self
-            .impl_blocks
-            .iter()
-            .find(|(_, methods)| methods.iter().any(|m| m.sig.ident == method_call.method))
-            .map(|(struct_name, _)| struct_name.clone())
-        {
-            Some(struct_name) => {
-                let qualified_method = format!("{}::{}", struct_name, method_name);
-                let caller = self.get_qualified_name(&self.current_function);
-                self.function_calls
-                    .push((caller.clone(), qualified_method.clone()));
-                self.process_method(&struct_name, &method_name);
+    // Macros aren't expanded, but arguments that parse as comma-separated expressions,
+    // as in `println!("{}", compute())`, are searched for calls like any others
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        match mac.parse_body_with(Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated) {
+            Ok(arguments) => {
+                for argument in &arguments {
+                    self.visit_expr(argument);
+                }
             }
-            None => (),
+            Err(_) => (),
         }
+        let segments = mac.path.segments.iter().map(|s| s.ident.to_string());
+        self.push(CallSite::Macro(segments.collect()), mac.path.span());
+        syn::visit::visit_macro(self, mac);
+    }
 
-        syn::visit::visit_expr_method_call(self, method_call);
+    // Nested functions are definitions of their own, so their calls are not this
+    // function's
+    fn visit_item(&mut self, item: &'ast Item) {
+        match item {
+            Item::Fn(_) => (),
+            _ => syn::visit::visit_item(self, item),
+        }
+    }
+
+    // `let f = || helper();` keeps the closure's calls for wherever `f()` is invoked
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        let name = match &local.pat {
+            syn::Pat::Ident(binding) => Some(binding.ident.to_string()),
+            syn::Pat::Type(pat_type) => match &*pat_type.pat {
+                syn::Pat::Ident(binding) => Some(binding.ident.to_string()),
+                _ => None,
+            },
+            _ => None,
+        };
+        let closure = local.init.as_ref().and_then(|init| match &*init.expr {
+            syn::Expr::Closure(closure) => Some(closure),
+            _ => None,
+        });
+
+        // A binding without a type annotation shadows whatever type the name had
+        match &local.pat {
+            syn::Pat::Ident(binding) => {
+                self.typed_bindings.remove(&binding.ident.to_string());
+            }
+            _ => (),
+        }
+
+        match (name, closure) {
+            (Some(name), Some(closure)) => {
+                let mut body = CallSiteCollector {
+                    trait_objects: self.trait_objects.clone(),
+                    typed_bindings: self.typed_bindings.clone(),
+                    ..Default::default()
+                };
+                body.visit_expr_closure(closure);
+                body.finish_closures();
+                self.closures.push((name, body.calls, false));
+            }
+            _ => syn::visit::visit_local(self, local),
+        }
+    }
+
+    // `x: &dyn Trait` or `x: Foo` in a signature or a `let`
+    fn visit_pat_type(&mut self, pat_type: &'ast syn::PatType) {
+        match (
+            &*pat_type.pat,
+            dyn_trait_of(&pat_type.ty),
+            named_type_of(&pat_type.ty),
+        ) {
+            (syn::Pat::Ident(binding), Some(trait_name), _) => {
+                self.trait_objects
+                    .insert(binding.ident.to_string(), trait_name);
+            }
+            (syn::Pat::Ident(binding), None, Some(type_path)) => {
+                self.typed_bindings
+                    .insert(binding.ident.to_string(), type_path);
+            }
+            _ => (),
+        }
+        syn::visit::visit_pat_type(self, pat_type);
+    }
+}
+
+// The trait of a `dyn Trait` type, also behind references and wrappers like
+// `Box<dyn Trait>` or `Arc<Mutex<dyn Trait>>`
+fn dyn_trait_of(ty: &syn::Type) -> Option<String> {
+    match ty {
+        syn::Type::TraitObject(object) => object.bounds.iter().find_map(|bound| match bound {
+            syn::TypeParamBound::Trait(bound) => {
+                bound.path.segments.last().map(|s| s.ident.to_string())
+            }
+            _ => None,
+        }),
+        syn::Type::Reference(reference) => dyn_trait_of(&reference.elem),
+        syn::Type::Paren(paren) => dyn_trait_of(&paren.elem),
+        syn::Type::Path(path) => match &path.path.segments.last()?.arguments {
+            syn::PathArguments::AngleBracketed(args) => {
+                args.args.iter().find_map(|arg| match arg {
+                    syn::GenericArgument::Type(ty) => dyn_trait_of(ty),
+                    _ => None,
+                })
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// The path of a named type like `Foo` or `io::Client`, also behind references, without
+// its type arguments
+fn named_type_of(ty: &syn::Type) -> Option<Vec<String>> {
+    match ty {
+        syn::Type::Path(path) if path.qself.is_none() => Some(
+            path.path
+                .segments
+                .iter()
+                .map(|s| s.ident.to_string())
+                .collect(),
+        ),
+        syn::Type::Reference(reference) => named_type_of(&reference.elem),
+        syn::Type::Paren(paren) => named_type_of(&paren.elem),
+        _ => None,
+    }
+}
+
+// The name methods of an `impl` for `ty` are keyed under: the last segment of a named
+// type, or for types without a name a key like `<slice u8>`, `<array u8>`,
+// `<tuple u8, bool>` or `<ref str>`
+fn impl_type_key(ty: &syn::Type) -> Option<String> {
+    let key = |ty: &syn::Type| impl_type_key(ty).unwrap_or_else(|| "_".to_string());
+    match ty {
+        syn::Type::Path(type_path) => type_path.path.segments.last().map(|s| s.ident.to_string()),
+        syn::Type::Paren(paren) => impl_type_key(&paren.elem),
+        syn::Type::Group(group) => impl_type_key(&group.elem),
+        syn::Type::Slice(slice) => Some(format!("<slice {}>", key(&slice.elem))),
+        syn::Type::Array(array) => Some(format!("<array {}>", key(&array.elem))),
+        syn::Type::Reference(reference) => Some(format!("<ref {}>", key(&reference.elem))),
+        syn::Type::Tuple(tuple) => {
+            let elems: Vec<String> = tuple.elems.iter().map(key).collect();
+            Some(format!("<tuple {}>", elems.join(", ")))
+        }
+        _ => None,
+    }
+}
+
+fn contains_unsafe_block(block: &syn::Block) -> bool {
+    let mut finder = UnsafeBlockFinder { found: false };
+    finder.visit_block(block);
+    finder.found
+}
+
+fn contains_unsafe_expr(expr: &syn::Expr) -> bool {
+    let mut finder = UnsafeBlockFinder { found: false };
+    finder.visit_expr(expr);
+    finder.found
+}
+
+struct UnsafeBlockFinder {
+    found: bool,
+}
+
+impl<'ast> Visit<'ast> for UnsafeBlockFinder {
+    fn visit_expr_unsafe(&mut self, _expr: &'ast syn::ExprUnsafe) {
+        self.found = true;
+    }
+
+    // A nested function's unsafe blocks are its own
+    fn visit_item_fn(&mut self, _func: &'ast syn::ItemFn) {}
+}
+
+// One plus the number of branch points in a function body
+fn cyclomatic_complexity(block: &syn::Block) -> usize {
+    let mut counter = BranchCounter { branches: 0 };
+    counter.visit_block(block);
+    1 + counter.branches
+}
+
+struct BranchCounter {
+    branches: usize,
+}
+
+impl<'ast> Visit<'ast> for BranchCounter {
+    fn visit_expr_if(&mut self, expr: &'ast syn::ExprIf) {
+        self.branches += 1;
+        syn::visit::visit_expr_if(self, expr);
+    }
+
+    fn visit_expr_match(&mut self, expr: &'ast syn::ExprMatch) {
+        // A match with n arms branches like n - 1 chained `if`s
+        self.branches += expr.arms.len().saturating_sub(1);
+        syn::visit::visit_expr_match(self, expr);
+    }
+
+    fn visit_expr_while(&mut self, expr: &'ast syn::ExprWhile) {
+        self.branches += 1;
+        syn::visit::visit_expr_while(self, expr);
+    }
+
+    fn visit_expr_for_loop(&mut self, expr: &'ast syn::ExprForLoop) {
+        self.branches += 1;
+        syn::visit::visit_expr_for_loop(self, expr);
+    }
+
+    fn visit_expr_binary(&mut self, expr: &'ast syn::ExprBinary) {
+        match expr.op {
+            syn::BinOp::And(_) | syn::BinOp::Or(_) => self.branches += 1,
+            _ => (),
+        }
+        syn::visit::visit_expr_binary(self, expr);
+    }
+
+    fn visit_expr_try(&mut self, expr: &'ast syn::ExprTry) {
+        self.branches += 1;
+        syn::visit::visit_expr_try(self, expr);
+    }
+
+    // Nested functions are not part of the enclosing function's control flow
+    fn visit_item_fn(&mut self, _func: &'ast syn::ItemFn) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reads `source` as the root of crate `t` and follows calls from `t::main`
+    fn analyze(source: &str) -> FunctionCallVisitor {
+        let mut visitor = FunctionCallVisitor::new("t");
+        visitor.current_module = vec!["t".to_string()];
+        let items = visitor.file_items(parse_file(source).unwrap());
+        for definition in items.definitions {
+            visitor.add_definition(definition);
+        }
+        visitor.current_module.clear();
+        visitor.process_function("t::main");
+        visitor
+    }
+
+    fn calls(visitor: &FunctionCallVisitor, caller: &str, callee: &str) -> bool {
+        visitor
+            .function_calls
+            .iter()
+            .any(|(from, to)| from == caller && to == callee)
+    }
+
+    #[test]
+    fn recursion_is_a_self_edge() {
+        let visitor = analyze(
+            "fn fact(n: u64) -> u64 { if n == 0 { 1 } else { n * fact(n - 1) } }
+             fn main() { fact(5); }",
+        );
+        assert!(calls(&visitor, "t::main", "t::fact"));
+        assert!(calls(&visitor, "t::fact", "t::fact"));
+    }
+
+    #[test]
+    fn try_call_is_recorded() {
+        let visitor = analyze(
+            "fn foo() -> Result<(), ()> { Ok(()) }
+             fn main() -> Result<(), ()> { foo()?; Ok(()) }",
+        );
+        assert!(calls(&visitor, "t::main", "t::foo"));
+    }
+
+    #[test]
+    fn method_chained_off_try_is_recorded() {
+        let visitor = analyze(
+            "struct S;
+             impl S { fn b(&self) {} }
+             fn a() -> Result<S, ()> { Ok(S) }
+             fn main() -> Result<(), ()> { a()?.b(); Ok(()) }",
+        );
+        assert!(calls(&visitor, "t::main", "t::a"));
+        assert!(calls(&visitor, "t::main", "t::S::b"));
+    }
+
+    #[test]
+    fn impl_blocks_of_one_type_share_its_key() {
+        let visitor = analyze(
+            "struct S;
+             impl S { fn first(&self) {} }
+             impl S { fn second(&self) {} }
+             fn main() { S.first(); S.second(); }",
+        );
+        assert!(visitor.struct_methods.contains_key("t::S::first"));
+        assert!(visitor.struct_methods.contains_key("t::S::second"));
+        assert!(calls(&visitor, "t::main", "t::S::first"));
+        assert!(calls(&visitor, "t::main", "t::S::second"));
+    }
+
+    // A method's key is its type's module-qualified key followed by the method name, and
+    // that one map is what path calls and method calls both resolve through
+    #[test]
+    fn method_keys_are_module_qualified_type_keys() {
+        let visitor = analyze(
+            "mod a { pub struct S; impl S { pub fn new() -> S { S } } }
+             mod b { pub struct S; impl S { pub fn run(&self) {} } }
+             fn main() { a::S::new(); b::S.run(); }",
+        );
+        let mut keys: Vec<&String> = visitor.struct_methods.keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["t::a::S::new", "t::b::S::run"]);
+        assert!(calls(&visitor, "t::main", "t::a::S::new"));
+        assert!(calls(&visitor, "t::main", "t::b::S::run"));
+    }
+
+    #[test]
+    fn nested_function_is_called_by_its_parent() {
+        let visitor = analyze(
+            "fn outer() { fn inner() {} inner(); }
+             fn main() { outer(); }",
+        );
+        assert!(visitor.functions.contains_key("t::outer::inner"));
+        assert!(calls(&visitor, "t::outer", "t::outer::inner"));
+    }
+
+    #[test]
+    fn closure_body_calls_are_recorded() {
+        let visitor = analyze(
+            "fn helper() {}
+             fn main() { let f = || helper(); f(); }",
+        );
+        assert!(calls(&visitor, "t::main", "t::helper"));
+    }
+
+    #[test]
+    fn qualified_path_call_resolves_to_the_type() {
+        let visitor = analyze(
+            "trait Greet { fn greet(&self); }
+             struct S;
+             impl Greet for S { fn greet(&self) {} }
+             fn main() { <S as Greet>::greet(&S); S::greet(&S); }",
+        );
+        let greets = visitor
+            .function_calls
+            .iter()
+            .filter(|(from, to)| from == "t::main" && to == "t::S::greet")
+            .count();
+        assert_eq!(greets, 2);
+    }
+
+    #[test]
+    fn const_initializer_calls_are_recorded() {
+        let mut visitor = analyze(
+            "const fn compute() -> u32 { 3 }
+             const X: u32 = compute();
+             fn main() {}",
+        );
+        assert_eq!(visitor.initializers, vec!["t::<const X>"]);
+        visitor.process_function("t::<const X>");
+        assert!(calls(&visitor, "t::<const X>", "t::compute"));
+    }
+
+    #[test]
+    fn slice_and_tuple_impls_get_synthetic_keys() {
+        let visitor = analyze(
+            "trait Sum { fn total(&self) -> u32; }
+             impl Sum for [u8] { fn total(&self) -> u32 { 0 } }
+             trait Swap { fn swapped(self) -> Self; }
+             impl Swap for (u8, bool) { fn swapped(self) -> Self { self } }
+             fn main() { let bytes: &[u8] = &[1]; bytes.total(); (1u8, true).swapped(); }",
+        );
+        assert!(visitor.struct_methods.contains_key("t::<slice u8>::total"));
+        assert!(
+            visitor
+                .struct_methods
+                .contains_key("t::<tuple u8, bool>::swapped")
+        );
+        assert!(calls(&visitor, "t::main", "t::<slice u8>::total"));
+        assert!(calls(&visitor, "t::main", "t::<tuple u8, bool>::swapped"));
+    }
+
+    #[test]
+    fn named_types_go_before_synthetic_keys() {
+        let visitor = analyze(
+            "struct Zed;
+             impl Zed { fn len(&self) -> usize { 0 } }
+             trait Len { fn len(&self) -> usize; }
+             impl Len for [u8] { fn len(&self) -> usize { 1 } }
+             fn main() { get().len(); }
+             fn get() -> Zed { Zed }",
+        );
+        assert!(calls(&visitor, "t::main", "t::Zed::len"));
     }
 }
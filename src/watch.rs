@@ -0,0 +1,70 @@
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::{
+    error::Error,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    time::Duration,
+};
+
+// How long changes must stop arriving before a run starts, so that saving several
+// files at once only triggers one
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+// Calls `run` now and again after every batch of `.rs` changes under any of `paths`,
+// forever. The flag passed to `run` is set as soon as another change arrives, so it can
+// give up on work that is already stale.
+pub fn watch(
+    paths: &[PathBuf],
+    mut run: impl FnMut(&AtomicBool) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let changed = Arc::new(AtomicBool::new(false));
+    let (sender, receiver) = mpsc::channel();
+
+    let flag = Arc::clone(&changed);
+    let mut watcher =
+        notify::recommended_watcher(move |event: notify::Result<Event>| match event {
+            Ok(event) if is_source_change(&event) => {
+                flag.store(true, Ordering::Relaxed);
+                let _ = sender.send(());
+            }
+            Ok(_) => (),
+            Err(e) => log::warn!("Error watching for changes: {}", e),
+        })?;
+    for path in paths {
+        watcher.watch(path, RecursiveMode::Recursive)?;
+    }
+    let watched: Vec<String> = paths
+        .iter()
+        .map(|path| format!("'{}'", path.display()))
+        .collect();
+
+    loop {
+        changed.store(false, Ordering::Relaxed);
+        match run(&changed) {
+            Ok(_) => (),
+            Err(e) => log::error!("{}", e),
+        }
+
+        log::info!("Watching {} for changes...", watched.join(", "));
+        receiver.recv()?;
+        while receiver.recv_timeout(DEBOUNCE).is_ok() {}
+    }
+}
+
+// A `.rs` file was created, changed or removed outside of any `target/` directory
+fn is_source_change(event: &Event) -> bool {
+    let relevant_kind = matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+    );
+
+    relevant_kind
+        && event.paths.iter().any(|path| {
+            path.extension().is_some_and(|ext| ext == "rs")
+                && !path.components().any(|c| c.as_os_str() == "target")
+        })
+}
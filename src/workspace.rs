@@ -0,0 +1,1566 @@
+use crate::{
+    cache::Cache,
+    progress::Progress,
+    utils,
+    visitor::{FunctionCallVisitor, SourceLocation},
+};
+use petgraph::{Graph, prelude::*};
+use regex::Regex;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub struct CargoConfig {
+    pub package_name: Option<String>,
+    pub workspace_members: Vec<PathBuf>,
+    pub dependencies: Vec<String>,
+    // Directories of dependencies given as `{ path = "../other" }`
+    pub path_dependencies: Vec<PathBuf>,
+    pub benches: Vec<PathBuf>,
+    // Integration tests: `[[test]]` entries plus anything auto-discovered in `tests/`
+    pub tests: Vec<PathBuf>,
+    pub examples: Vec<PathBuf>,
+    // `None` when the package inherits the edition of its workspace
+    pub edition: Option<String>,
+    // `[workspace.package] edition`, for members to inherit
+    pub workspace_edition: Option<String>,
+}
+
+// What we assume for packages that do not say
+pub const DEFAULT_EDITION: &str = "2021";
+
+impl CargoConfig {
+    pub fn from_path(dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let manifest = fs::read_to_string(dir.join("Cargo.toml"))?;
+        let table: toml::Table = manifest.parse()?;
+
+        let package_name = table
+            .get("package")
+            .and_then(|package| package.get("name"))
+            .and_then(|name| name.as_str())
+            .map(utils::normalize_crate_name);
+
+        let edition = match table
+            .get("package")
+            .and_then(|package| package.get("edition"))
+        {
+            Some(toml::Value::String(edition)) => Some(edition.clone()),
+            // `edition.workspace = true`
+            Some(_) => None,
+            None => Some(DEFAULT_EDITION.to_string()),
+        };
+        let workspace_edition = table
+            .get("workspace")
+            .and_then(|workspace| workspace.get("package"))
+            .and_then(|package| package.get("edition"))
+            .and_then(|edition| edition.as_str())
+            .map(|edition| edition.to_string());
+
+        let mut workspace_members = Vec::new();
+        let members = table
+            .get("workspace")
+            .and_then(|workspace| workspace.get("members"))
+            .and_then(|members| members.as_array());
+        for member in members.into_iter().flatten().filter_map(|m| m.as_str()) {
+            match member.strip_suffix("/*") {
+                // Glob members like `crates/*` include every crate directory beneath
+                Some(parent) => {
+                    for entry in fs::read_dir(dir.join(parent))?.filter_map(|e| e.ok()) {
+                        if entry.path().join("Cargo.toml").exists() {
+                            workspace_members.push(entry.path());
+                        }
+                    }
+                }
+                None => workspace_members.push(dir.join(member)),
+            }
+        }
+        workspace_members.sort();
+
+        let dependencies = table
+            .get("dependencies")
+            .and_then(|deps| deps.as_table())
+            .map(|deps| {
+                deps.keys()
+                    .map(|k| utils::normalize_crate_name(k))
+                    .collect()
+            })
+            .unwrap_or_default();
+        // Path dependencies may also be declared in `[dev-dependencies]` or, for a workspace,
+        // in `[workspace.dependencies]`
+        let path_dependencies = [
+            table.get("dependencies"),
+            table.get("dev-dependencies"),
+            table
+                .get("workspace")
+                .and_then(|workspace| workspace.get("dependencies")),
+        ]
+        .into_iter()
+        .flatten()
+        .filter_map(|deps| deps.as_table())
+        .flat_map(|deps| deps.values())
+        .filter_map(|dep| dep.get("path").and_then(|path| path.as_str()))
+        .map(|path| dir.join(path))
+        .collect();
+
+        let benches = targets(&table, dir, "bench", "benches");
+        let tests = targets(&table, dir, "test", "tests");
+        let examples = targets(&table, dir, "example", "examples");
+
+        Ok(CargoConfig {
+            package_name,
+            workspace_members,
+            dependencies,
+            path_dependencies,
+            benches,
+            tests,
+            examples,
+            edition,
+            workspace_edition,
+        })
+    }
+}
+
+// Targets of one kind, like benches: `[[bench]]` entries plus anything Cargo discovers
+// in `benches/` unless `autobenches = false`
+fn targets(table: &toml::Table, dir: &Path, kind: &str, target_dir: &str) -> Vec<PathBuf> {
+    let mut targets: Vec<PathBuf> = table
+        .get(kind)
+        .and_then(|targets| targets.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|target| {
+            match (
+                target.get("path").and_then(|path| path.as_str()),
+                target.get("name").and_then(|name| name.as_str()),
+            ) {
+                (Some(path), _) => Some(dir.join(path)),
+                (None, Some(name)) => Some(dir.join(target_dir).join(format!("{}.rs", name))),
+                (None, None) => None,
+            }
+        })
+        .collect();
+    let autodiscover = table
+        .get("package")
+        .and_then(|package| package.get(format!("auto{}", target_dir)))
+        .and_then(|autodiscover| autodiscover.as_bool())
+        .unwrap_or(true);
+    if autodiscover {
+        targets.extend(discover_targets(&dir.join(target_dir)));
+    }
+    targets.retain(|target| target.exists());
+    targets.sort();
+    targets.dedup();
+    targets
+}
+
+// Target files Cargo discovers automatically in a directory like `benches/`:
+// each `*.rs` file and each `*/main.rs`
+fn discover_targets(dir: &Path) -> Vec<PathBuf> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    entries
+        .filter_map(|e| e.ok())
+        .map(|entry| entry.path())
+        .filter_map(|path| match path.is_dir() {
+            true => Some(path.join("main.rs")).filter(|main| main.exists()),
+            false => Some(path).filter(|file| file.extension().is_some_and(|ext| ext == "rs")),
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryMode {
+    /// Start from the configured start functions, falling back to the public API
+    /// for crates without a `main`
+    Start,
+    /// Start from every `pub fn` and public method
+    Pub,
+}
+
+// Why a function was followed as an entry point
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EntryKind {
+    // A binary's `main`
+    Main,
+    // A function of an integration test, or one marked `#[test]`
+    Test,
+    // A function of a bench target, or one marked `#[bench]`
+    Bench,
+}
+
+#[derive(Clone)]
+pub struct AnalysisConfig {
+    // The functions to follow calls from, when asked for (`--start`, `--root`); `main`
+    // otherwise
+    pub start_functions: Option<Vec<String>>,
+    pub entry_mode: EntryMode,
+    pub respect_ignore_files: bool,
+    pub include_benches: bool,
+    // Also follow calls from integration tests (`tests/`)
+    pub include_tests: bool,
+    // Also follow calls from examples (`examples/`)
+    pub include_examples: bool,
+    // Also analyze crates depended on by path that aren't workspace members
+    pub include_external_crates: bool,
+    // Workspace members to leave out entirely, as though they were not members
+    pub exclude_crates: Vec<String>,
+    // Where to cache what is extracted from each source file, if anywhere
+    pub cache_dir: Option<PathBuf>,
+    // Show a progress bar while analyzing, if stderr is a terminal
+    pub progress: bool,
+    // Also record calls into `std`, `core` and `alloc`
+    pub include_std: bool,
+    // How many calls deep to follow from each entry point; unlimited when `None`
+    pub max_depth: Option<usize>,
+    // Only follow calls into functions whose qualified name matches
+    pub only: Option<Regex>,
+}
+
+impl Default for AnalysisConfig {
+    fn default() -> Self {
+        AnalysisConfig {
+            start_functions: None,
+            entry_mode: EntryMode::Start,
+            respect_ignore_files: true,
+            include_benches: false,
+            include_tests: false,
+            include_examples: false,
+            include_external_crates: false,
+            exclude_crates: Vec::new(),
+            cache_dir: None,
+            progress: false,
+            include_std: false,
+            max_depth: None,
+            only: None,
+        }
+    }
+}
+
+impl AnalysisConfig {
+    fn explicit_start(&self) -> bool {
+        self.start_functions.is_some()
+    }
+
+    fn starts(&self) -> Vec<String> {
+        match &self.start_functions {
+            Some(starts) => starts.clone(),
+            None => vec!["main".to_string()],
+        }
+    }
+}
+
+struct CrateInfo {
+    name: String,
+    path: PathBuf,
+    dependencies: Vec<String>,
+    benches: Vec<PathBuf>,
+    tests: Vec<PathBuf>,
+    examples: Vec<PathBuf>,
+    edition: String,
+}
+
+pub struct WorkspaceAnalyzer {
+    config: AnalysisConfig,
+    crates: Vec<CrateInfo>,
+}
+
+impl WorkspaceAnalyzer {
+    pub fn new(root: &Path, config: AnalysisConfig) -> Result<Self, Box<dyn Error>> {
+        let root_config = CargoConfig::from_path(root)?;
+        let mut crates = Vec::new();
+
+        let excluded = |name: &str| {
+            config
+                .exclude_crates
+                .iter()
+                .any(|exclude| utils::normalize_crate_name(exclude) == name)
+        };
+
+        let workspace_edition = root_config.workspace_edition.clone();
+        let mut add_crate = |path: &Path, cargo: CargoConfig| match cargo.package_name {
+            Some(name) if excluded(&name) => log::info!("Excluding crate '{}'", name),
+            Some(name) => crates.push(CrateInfo {
+                name,
+                path: path.to_path_buf(),
+                dependencies: cargo.dependencies,
+                benches: cargo.benches,
+                tests: cargo.tests,
+                examples: cargo.examples,
+                edition: cargo
+                    .edition
+                    .or_else(|| workspace_edition.clone())
+                    .unwrap_or_else(|| DEFAULT_EDITION.to_string()),
+            }),
+            None => (),
+        };
+
+        let members = root_config.workspace_members.clone();
+        let mut path_dependencies = root_config.path_dependencies.clone();
+        add_crate(root, root_config);
+        for member in &members {
+            let member_config = CargoConfig::from_path(member)?;
+            path_dependencies.extend(member_config.path_dependencies.clone());
+            add_crate(member, member_config);
+        }
+
+        // Path dependencies outside the workspace, and in turn their own
+        let canonical = |dir: &Path| dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        let mut seen: HashSet<PathBuf> = members.iter().map(|member| canonical(member)).collect();
+        seen.insert(canonical(root));
+        if config.include_external_crates {
+            while let Some(dir) = path_dependencies.pop() {
+                if !seen.insert(canonical(&dir)) {
+                    continue;
+                }
+                match CargoConfig::from_path(&dir) {
+                    Ok(dependency_config) => {
+                        log::info!("Including path dependency '{}'", dir.display());
+                        path_dependencies.extend(dependency_config.path_dependencies.clone());
+                        add_crate(&dir, dependency_config);
+                    }
+                    Err(e) => log::warn!("Skipping path dependency '{}': {}", dir.display(), e),
+                }
+            }
+        }
+
+        Ok(WorkspaceAnalyzer { config, crates })
+    }
+
+    // Adds the crates of another repository's analyzer, so that they are analyzed as
+    // members of this workspace. A crate whose name is already taken is left out, since
+    // its functions would be indistinguishable.
+    pub fn merge(&mut self, other: WorkspaceAnalyzer) {
+        for krate in other.crates {
+            match self.crates.iter().any(|c| c.name == krate.name) {
+                true => log::warn!(
+                    "Skipping crate '{}' in '{}': a crate of that name was already found",
+                    krate.name,
+                    krate.path.display()
+                ),
+                false => self.crates.push(krate),
+            }
+        }
+    }
+
+    // A visitor for the crate with its sources loaded. Workspace dependencies are loaded
+    // into the same visitor so that calls into them resolve to their crate-prefixed functions
+    fn load_crate(&self, krate: &CrateInfo) -> Result<FunctionCallVisitor, Box<dyn Error>> {
+        let mut visitor = new_visitor(&krate.name, &self.config);
+        utils::analyze_directory(
+            &mut visitor,
+            &krate.name,
+            &krate.path,
+            self.config.respect_ignore_files,
+        )?;
+
+        for dependency in self
+            .crates
+            .iter()
+            .filter(|c| krate.dependencies.contains(&c.name))
+        {
+            utils::analyze_directory(
+                &mut visitor,
+                &dependency.name,
+                &dependency.path,
+                self.config.respect_ignore_files,
+            )?;
+        }
+        visitor.dependency_crates = krate
+            .dependencies
+            .iter()
+            .filter(|dependency| !visitor.loaded_crates.contains(*dependency))
+            .cloned()
+            .collect();
+
+        Ok(visitor)
+    }
+
+    // Targets like benches are compiled separately against the package. Each gets its own
+    // visitor with its functions under `<crate>::<kind>::<target>`, all of them entry points.
+    fn analyze_target(
+        &self,
+        krate: &CrateInfo,
+        kind: &str,
+        file: &Path,
+    ) -> Result<(FunctionCallVisitor, Vec<String>), Box<dyn Error>> {
+        let mut visitor = self.load_crate(krate)?;
+
+        // `benches/foo/main.rs` is the `foo` target; `process_module` adds the module
+        // for `benches/foo.rs` itself
+        let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+        let target = match stem == "main" {
+            true => file
+                .parent()
+                .and_then(|parent| parent.file_name())
+                .unwrap_or_default()
+                .to_string_lossy(),
+            false => stem.clone(),
+        };
+        visitor.current_module = vec![krate.name.clone(), kind.to_string()];
+        if stem == "main" {
+            visitor.current_module.push(target.to_string());
+        }
+        visitor.process_module(file)?;
+        visitor.current_module.clear();
+
+        let prefix = format!("{}::{}::{}::", krate.name, kind, target);
+        let mut entries: Vec<String> = visitor
+            .functions
+            .keys()
+            .chain(visitor.struct_methods.keys())
+            .filter(|name| name.starts_with(&prefix))
+            .cloned()
+            .collect();
+        entries.sort();
+
+        for entry in &entries {
+            follow_entry_point(&mut visitor, entry);
+        }
+
+        Ok((visitor, entries))
+    }
+
+    pub fn analyze_workspace(&self) -> Result<WorkspaceAnalysis, Box<dyn Error>> {
+        log::info!(
+            "Starting workspace analysis of {} crate(s)...",
+            self.crates.len()
+        );
+
+        // One step per crate, bench, test and example
+        let total = self.crates.len()
+            + match self.config.include_benches {
+                true => self.crates.iter().map(|krate| krate.benches.len()).sum(),
+                false => 0,
+            }
+            + match self.config.include_tests {
+                true => self.crates.iter().map(|krate| krate.tests.len()).sum(),
+                false => 0,
+            }
+            + match self.config.include_examples {
+                true => self.crates.iter().map(|krate| krate.examples.len()).sum(),
+                false => 0,
+            };
+        let progress = Progress::start(self.config.progress, total);
+
+        // Every crate is read before any calls are followed, since whether a crate
+        // without a start function falls back to its public API depends on the others
+        let crate_visitors = self
+            .crates
+            .iter()
+            .map(|krate| {
+                log::info!(
+                    "Analyzing crate '{}' (edition {})",
+                    krate.name,
+                    krate.edition
+                );
+                progress.set_message(krate.name.clone());
+                self.load_crate(krate)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let fallback = public_fallback(&self.config, &crate_visitors);
+
+        let mut visitors = Vec::new();
+        let mut test_entry_points = Vec::new();
+        let mut bench_entry_points = Vec::new();
+        for (krate, mut visitor) in self.crates.iter().zip(crate_visitors) {
+            let (tests, benches) =
+                split_entry_points(follow_entry_points(&self.config, &mut visitor, fallback));
+            test_entry_points.extend(tests);
+            bench_entry_points.extend(benches);
+            visitors.push(visitor);
+            progress.inc();
+
+            if self.config.include_benches {
+                for bench in &krate.benches {
+                    log::info!("Analyzing bench '{}'", bench.display());
+                    progress.set_message(bench.display().to_string());
+                    let (visitor, entries) = self.analyze_target(krate, "benches", bench)?;
+                    visitors.push(visitor);
+                    bench_entry_points.extend(entries);
+                    progress.inc();
+                }
+            }
+
+            if self.config.include_tests {
+                for test in &krate.tests {
+                    log::info!("Analyzing integration test '{}'", test.display());
+                    progress.set_message(test.display().to_string());
+                    let (visitor, entries) = self.analyze_target(krate, "tests", test)?;
+                    visitors.push(visitor);
+                    test_entry_points.extend(entries);
+                    progress.inc();
+                }
+            }
+
+            if self.config.include_examples {
+                for example in &krate.examples {
+                    log::info!("Analyzing example '{}'", example.display());
+                    progress.set_message(example.display().to_string());
+                    visitors.push(self.analyze_target(krate, "examples", example)?.0);
+                    progress.inc();
+                }
+            }
+        }
+
+        warn_missing_start_functions(&self.config, &visitors);
+
+        Ok(WorkspaceAnalysis {
+            crate_names: self.crates.iter().map(|c| c.name.clone()).collect(),
+            visitors,
+            test_entry_points,
+            bench_entry_points,
+        })
+    }
+}
+
+// A bare start name matches every function or method of that name in the crate,
+// while a qualified one (`server::run` or `mycrate::server::run`) matches exactly
+fn matching_start_functions(config: &AnalysisConfig, visitor: &FunctionCallVisitor) -> Vec<String> {
+    let crate_name = &visitor.crate_name;
+    let starts = config.starts();
+    let mut entries: Vec<String> = visitor
+        .functions
+        .keys()
+        .chain(visitor.struct_methods.keys())
+        .filter(|name| utils::crate_of(name) == crate_name)
+        .filter(|name| {
+            starts
+                .iter()
+                .any(|start| is_start_function(name, start, crate_name))
+        })
+        .cloned()
+        .collect();
+    entries.sort();
+    entries.dedup();
+    entries
+}
+
+// Whether a crate without a start function starts from its public API instead: always
+// for the default `main`, but for start functions that were asked for only when no
+// crate has any of them, so that they narrow the graph rather than add to it
+fn public_fallback(config: &AnalysisConfig, visitors: &[FunctionCallVisitor]) -> bool {
+    !config.explicit_start()
+        || visitors
+            .iter()
+            .all(|visitor| matching_start_functions(config, visitor).is_empty())
+}
+
+fn find_start_functions(
+    config: &AnalysisConfig,
+    visitor: &FunctionCallVisitor,
+    fallback: bool,
+) -> Vec<String> {
+    let crate_name = &visitor.crate_name;
+    let entries = matching_start_functions(config, visitor);
+    let has_main = visitor
+        .functions
+        .contains_key(&format!("{}::main", crate_name));
+    match (
+        config.entry_mode,
+        entries.is_empty() && !has_main,
+        fallback,
+        config.explicit_start(),
+    ) {
+        (EntryMode::Pub, _, _, _) => find_public_functions(visitor),
+        (EntryMode::Start, true, false, _) => Vec::new(),
+        (EntryMode::Start, true, true, true) => {
+            log::warn!(
+                "No start function ({}) matched in crate '{}', starting from its public API instead",
+                config.starts().join(", "),
+                crate_name
+            );
+            find_public_functions(visitor)
+        }
+        (EntryMode::Start, true, true, false) => {
+            log::info!(
+                "Crate '{}' has no main, starting from its public API",
+                crate_name
+            );
+            find_public_functions(visitor)
+        }
+        (EntryMode::Start, false, _, _) => entries,
+    }
+}
+
+fn is_start_function(name: &str, start: &str, crate_name: &str) -> bool {
+    match start.contains("::") {
+        true => name == start || name == format!("{}::{}", crate_name, start),
+        false => name.rsplit("::").next() == Some(start),
+    }
+}
+
+// Warns about start functions that match nothing, so a typo doesn't just silently
+// produce an empty graph
+fn warn_missing_start_functions(config: &AnalysisConfig, visitors: &[FunctionCallVisitor]) {
+    if config.entry_mode != EntryMode::Start {
+        return;
+    }
+    for start in &config.starts() {
+        let found = visitors.iter().any(|visitor| {
+            visitor
+                .functions
+                .keys()
+                .chain(visitor.struct_methods.keys())
+                .any(|name| is_start_function(name, start, &visitor.crate_name))
+        });
+        if !found {
+            log::warn!("Start function '{}' not found in any crate", start);
+        }
+    }
+}
+
+// The crate's public API: every `pub fn` and public method
+fn find_public_functions(visitor: &FunctionCallVisitor) -> Vec<String> {
+    let is_pub = |name: &String| {
+        visitor
+            .visibilities
+            .get(name)
+            .is_some_and(|vis| vis == "pub")
+    };
+
+    let mut entries: Vec<String> = visitor
+        .functions
+        .keys()
+        .chain(visitor.struct_methods.keys())
+        .filter(|name| is_pub(name))
+        .filter(|name| utils::crate_of(name) == visitor.crate_name)
+        .cloned()
+        .collect();
+    entries.sort();
+    entries
+}
+
+// The crate's functions marked `#[test]` or `#[bench]` (or e.g. `#[tokio::test]`),
+// for whichever of tests and benches are included
+fn find_annotated_functions(
+    config: &AnalysisConfig,
+    visitor: &FunctionCallVisitor,
+) -> Vec<(String, EntryKind)> {
+    let mut entries: Vec<(String, EntryKind)> = visitor
+        .attributes
+        .iter()
+        .filter(|(name, _)| utils::crate_of(name) == visitor.crate_name)
+        .filter_map(|(name, attributes)| {
+            let marked = |attribute: &str| {
+                attributes
+                    .iter()
+                    .any(|path| utils::attribute_matches(path, attribute))
+            };
+            match (marked("test"), marked("bench")) {
+                (true, _) if config.include_tests => Some((name.clone(), EntryKind::Test)),
+                (_, true) if config.include_benches => Some((name.clone(), EntryKind::Bench)),
+                _ => None,
+            }
+        })
+        .collect();
+    entries.sort();
+    entries
+}
+
+// Follows the start functions, then the `#[test]` and `#[bench]` functions, which are
+// returned with their kind
+fn follow_entry_points(
+    config: &AnalysisConfig,
+    visitor: &mut FunctionCallVisitor,
+    fallback: bool,
+) -> Vec<(String, EntryKind)> {
+    let entries = find_start_functions(config, visitor, fallback);
+    for entry in &entries {
+        follow_entry_point(visitor, entry);
+    }
+
+    let mut annotated = find_annotated_functions(config, visitor);
+    annotated.retain(|(name, _)| !entries.contains(name));
+    for (entry, _) in &annotated {
+        follow_entry_point(visitor, entry);
+    }
+
+    // Whatever calls them, `const` and `static` initializers run, unless the graph was
+    // asked to start somewhere in particular
+    let mut initializers = match config.explicit_start() {
+        true => Vec::new(),
+        false => visitor.initializers.clone(),
+    };
+    initializers.retain(|initializer| !entries.contains(initializer));
+    initializers.sort();
+    for initializer in initializers {
+        follow_entry_point(visitor, &initializer);
+    }
+
+    annotated
+}
+
+// The test entry points and the bench entry points
+fn split_entry_points(entries: Vec<(String, EntryKind)>) -> (Vec<String>, Vec<String>) {
+    let mut tests = Vec::new();
+    let mut benches = Vec::new();
+    for (entry, kind) in entries {
+        match kind {
+            EntryKind::Test => tests.push(entry),
+            EntryKind::Bench => benches.push(entry),
+            EntryKind::Main => (),
+        }
+    }
+    (tests, benches)
+}
+
+fn follow_entry_point(visitor: &mut FunctionCallVisitor, entry: &str) {
+    log::debug!("Following calls from '{}'", entry);
+    visitor.followed_entries.push(entry.to_string());
+    let calls_before = visitor.function_calls.len();
+    match visitor.functions.contains_key(entry) {
+        true => visitor.process_function(entry),
+        false => match entry.rsplit_once("::") {
+            Some((type_name, method_name)) => visitor.process_method(type_name, method_name),
+            None => (),
+        },
+    }
+    if visitor.function_calls.len() == calls_before {
+        log::info!("Entry point '{}' made no calls", entry);
+    }
+}
+
+// A function or method found anywhere in the analyzed code, whether or not it is
+// ever called
+#[derive(Clone, Debug)]
+pub struct FunctionInfo {
+    pub qualified_name: String,
+    pub file: String,
+    pub line: usize,
+    // `pub`, `pub(crate)` and so on, or `private`
+    pub visibility: String,
+    pub is_async: bool,
+    pub is_unsafe: bool,
+    // Whether its body has an `unsafe { }` block
+    pub contains_unsafe: bool,
+}
+
+pub struct WorkspaceAnalysis {
+    pub crate_names: Vec<String>,
+    pub visitors: Vec<FunctionCallVisitor>,
+    // The functions of integration tests and those marked `#[test]`, which are
+    // followed as entry points
+    pub test_entry_points: Vec<String>,
+    // Likewise for bench targets and `#[bench]` functions
+    pub bench_entry_points: Vec<String>,
+}
+
+impl WorkspaceAnalysis {
+    // Calls from every crate's visitor in order. A call already recorded by an earlier
+    // visitor (e.g. two binaries tracing into the same library) is not repeated.
+    pub fn function_calls(&self) -> Vec<(String, String)> {
+        let mut seen = HashSet::new();
+        let mut calls = Vec::new();
+
+        for visitor in &self.visitors {
+            calls.extend(
+                visitor
+                    .function_calls
+                    .iter()
+                    .filter(|call| !seen.contains(*call))
+                    .cloned(),
+            );
+            seen.extend(visitor.function_calls.iter().cloned());
+        }
+
+        calls
+    }
+
+    // Signatures of every function and method known to any crate's visitor
+    pub fn signatures(&self) -> HashMap<String, String> {
+        self.visitors
+            .iter()
+            .flat_map(|visitor| visitor.signatures.clone())
+            .collect()
+    }
+
+    // Lines of code of every function and method known to any crate's visitor
+    pub fn lines_of_code(&self) -> HashMap<String, usize> {
+        self.visitors
+            .iter()
+            .flat_map(|visitor| visitor.lines_of_code.clone())
+            .collect()
+    }
+
+    // Cyclomatic complexity of every function and method known to any crate's visitor
+    pub fn complexity(&self) -> HashMap<String, usize> {
+        self.visitors
+            .iter()
+            .flat_map(|visitor| visitor.complexity.clone())
+            .collect()
+    }
+
+    // Every function and method defined in the analyzed code, sorted by name
+    pub fn functions(&self) -> Vec<FunctionInfo> {
+        let mut functions: HashMap<&String, FunctionInfo> = HashMap::new();
+        for visitor in &self.visitors {
+            for (name, location) in visitor
+                .locations
+                .iter()
+                .filter(|(name, _)| !self.is_initializer(name))
+            {
+                functions.entry(name).or_insert_with(|| FunctionInfo {
+                    qualified_name: name.clone(),
+                    file: location.file.clone(),
+                    line: location.line,
+                    visibility: visitor.visibilities.get(name).cloned().unwrap_or_default(),
+                    is_async: visitor.async_functions.contains(name),
+                    is_unsafe: visitor.unsafe_functions.contains(name),
+                    contains_unsafe: visitor.unsafe_block_functions.contains(name),
+                });
+            }
+        }
+
+        let mut functions: Vec<FunctionInfo> = functions.into_values().collect();
+        functions.sort_by(|a, b| a.qualified_name.cmp(&b.qualified_name));
+        functions
+    }
+
+    // Definition site of every function and method known to any crate's visitor
+    pub fn locations(&self) -> HashMap<String, SourceLocation> {
+        self.visitors
+            .iter()
+            .flat_map(|visitor| visitor.locations.clone())
+            .collect()
+    }
+
+    // The attributes of each function that has any besides doc comments
+    pub fn attributes(&self) -> HashMap<String, Vec<String>> {
+        self.visitors
+            .iter()
+            .flat_map(|visitor| visitor.attributes.clone())
+            .collect()
+    }
+
+    // The first line of each function's doc comment, where it has one
+    pub fn docs(&self) -> HashMap<String, String> {
+        self.visitors
+            .iter()
+            .flat_map(|visitor| visitor.docs.clone())
+            .collect()
+    }
+
+    pub fn visibilities(&self) -> HashMap<String, String> {
+        self.visitors
+            .iter()
+            .flat_map(|visitor| visitor.visibilities.clone())
+            .collect()
+    }
+
+    // The standard library functions and macros called, with `--include-std`
+    pub fn std_calls(&self) -> HashSet<String> {
+        self.visitors
+            .iter()
+            .flat_map(|visitor| visitor.std_calls.iter().cloned())
+            .collect()
+    }
+
+    // Functions called with explicit type arguments and each distinct set of them,
+    // those with the most instantiations first
+    pub fn generic_instantiations(&self) -> Vec<(String, Vec<String>)> {
+        let mut instantiations: HashMap<String, HashSet<String>> = HashMap::new();
+        for visitor in &self.visitors {
+            for (name, type_arguments) in &visitor.instantiations {
+                instantiations
+                    .entry(name.clone())
+                    .or_default()
+                    .extend(type_arguments.iter().cloned());
+            }
+        }
+
+        let mut instantiations: Vec<(String, Vec<String>)> = instantiations
+            .into_iter()
+            .map(|(name, type_arguments)| {
+                let mut type_arguments: Vec<String> = type_arguments.into_iter().collect();
+                type_arguments.sort();
+                (name, type_arguments)
+            })
+            .collect();
+        instantiations.sort_by(|(a, a_args), (b, b_args)| {
+            b_args.len().cmp(&a_args.len()).then_with(|| a.cmp(b))
+        });
+        instantiations
+    }
+
+    // Calls through trait objects, which may or may not reach the method they point at
+    pub fn possible_calls(&self) -> HashSet<(String, String)> {
+        self.visitors
+            .iter()
+            .flat_map(|visitor| visitor.possible_calls.iter().cloned())
+            .collect()
+    }
+
+    // Every `unsafe fn` and every function with an `unsafe { }` block, sorted
+    pub fn unsafe_functions(&self) -> Vec<String> {
+        let mut functions: Vec<String> = self
+            .visitors
+            .iter()
+            .flat_map(|visitor| {
+                visitor
+                    .unsafe_functions
+                    .iter()
+                    .chain(&visitor.unsafe_block_functions)
+                    .cloned()
+            })
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        functions.sort();
+        functions
+    }
+
+    // How many of the call sites followed resolved to a call, and how many there were
+    pub fn resolved_calls(&self) -> (usize, usize) {
+        let mut call_sites = HashSet::new();
+        let mut resolved = HashSet::new();
+        for visitor in &self.visitors {
+            call_sites.extend(&visitor.call_sites);
+            resolved.extend(&visitor.resolved_call_sites);
+        }
+        (resolved.len(), call_sites.len())
+    }
+
+    // How many call sites went into the standard library or a dependency that isn't
+    // analyzed, which `resolved_calls` leaves out
+    pub fn outside_calls(&self) -> usize {
+        self.visitors
+            .iter()
+            .flat_map(|visitor| &visitor.outside_call_sites)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    // How many distinct source files were read, across all crates and targets
+    pub fn files_analyzed(&self) -> usize {
+        self.visitors
+            .iter()
+            .flat_map(|visitor| &visitor.visited_files)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    // Whether any crate's visitor found a definition of the function or method
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.visitors.iter().any(|visitor| {
+            visitor.functions.contains_key(name) || visitor.struct_methods.contains_key(name)
+        })
+    }
+
+    // Whether a name is a `<const X>` or `<static Y>` initializer rather than a function
+    pub fn is_initializer(&self, name: &str) -> bool {
+        self.visitors.iter().any(|visitor| {
+            visitor
+                .initializers
+                .iter()
+                .any(|initializer| initializer == name)
+        })
+    }
+
+    // Every `async fn` and `async` method, sorted
+    pub fn async_functions(&self) -> Vec<String> {
+        let mut functions: Vec<String> = self
+            .visitors
+            .iter()
+            .flat_map(|visitor| visitor.async_functions.iter().cloned())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        functions.sort();
+        functions
+    }
+
+    // Every function exposed over FFI, sorted
+    pub fn ffi_functions(&self) -> Vec<String> {
+        let mut functions: Vec<String> = self
+            .visitors
+            .iter()
+            .flat_map(|visitor| visitor.ffi_functions.iter().cloned())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        functions.sort();
+        functions
+    }
+
+    // The `limit` most complex functions, most complex first, ties broken by name
+    pub fn most_complex_functions(&self, limit: usize) -> Vec<(String, usize)> {
+        let mut functions: Vec<(String, usize)> = self
+            .complexity()
+            .into_iter()
+            .filter(|(name, _)| !self.is_initializer(name))
+            .collect();
+        functions.sort_by(|(a_name, a), (b_name, b)| b.cmp(a).then_with(|| a_name.cmp(b_name)));
+        functions.truncate(limit);
+        functions
+    }
+
+    // The module a function or method is defined in, e.g. `app::net` for both
+    // `app::net::connect` and `app::net::Client::send`
+    pub fn module_of(&self, name: &str) -> String {
+        let is_method = self
+            .visitors
+            .iter()
+            .any(|visitor| visitor.struct_methods.contains_key(name));
+        let segments: Vec<&str> = name.split("::").collect();
+        let depth = match is_method {
+            true => 2,
+            false => 1,
+        };
+
+        match segments.len() > depth {
+            true => segments[..segments.len() - depth].join("::"),
+            false => name.to_string(),
+        }
+    }
+
+    // How many functions and methods are defined in each module, as `module_of`
+    // names them
+    pub fn functions_per_module(&self) -> BTreeMap<String, usize> {
+        let names: HashSet<String> = self
+            .visitors
+            .iter()
+            .flat_map(|visitor| {
+                visitor
+                    .functions
+                    .keys()
+                    .chain(visitor.struct_methods.keys())
+            })
+            .filter(|name| !self.is_initializer(name))
+            .cloned()
+            .collect();
+
+        let mut counts = BTreeMap::new();
+        for name in names {
+            *counts.entry(self.module_of(&name)).or_default() += 1;
+        }
+        counts
+    }
+
+    // The type each method belongs to, e.g. `app::net::Client` for `app::net::Client::send`
+    pub fn method_types(&self) -> HashMap<String, String> {
+        self.visitors
+            .iter()
+            .flat_map(|visitor| visitor.struct_methods.keys())
+            .filter_map(|name| {
+                name.rsplit_once("::")
+                    .map(|(type_name, _)| (name.clone(), type_name.to_string()))
+            })
+            .collect()
+    }
+
+    // Method names defined on more than one type, with those types, the most widely
+    // defined first. A call like `x.run()` on an untyped receiver goes to just one of them.
+    pub fn method_collisions(&self) -> Vec<(String, Vec<String>)> {
+        let mut types: HashMap<String, HashSet<String>> = HashMap::new();
+        for (method, type_name) in self.method_types() {
+            match method.rsplit_once("::") {
+                Some((_, method_name)) => {
+                    types
+                        .entry(method_name.to_string())
+                        .or_default()
+                        .insert(type_name);
+                }
+                None => (),
+            }
+        }
+
+        let mut collisions: Vec<(String, Vec<String>)> = types
+            .into_iter()
+            .filter(|(_, types)| types.len() > 1)
+            .map(|(method_name, types)| {
+                let mut types: Vec<String> = types.into_iter().collect();
+                types.sort();
+                (method_name, types)
+            })
+            .collect();
+        collisions.sort_by(|(a, a_types), (b, b_types)| {
+            b_types.len().cmp(&a_types.len()).then_with(|| a.cmp(b))
+        });
+        collisions
+    }
+
+    // The lines each call is made on, in the caller's file, keyed by caller and callee
+    pub fn call_lines(&self) -> HashMap<(String, String), Vec<usize>> {
+        let mut call_lines: HashMap<(String, String), Vec<usize>> = HashMap::new();
+        for visitor in &self.visitors {
+            for (call, &line) in visitor.function_calls.iter().zip(&visitor.call_lines) {
+                call_lines.entry(call.clone()).or_default().push(line);
+            }
+        }
+        for lines in call_lines.values_mut() {
+            lines.retain(|&line| line > 0);
+            lines.sort();
+            lines.dedup();
+        }
+        call_lines
+    }
+
+    // Pairs of modules and how many distinct caller/callee pairs cross between them in
+    // either direction, the most tightly coupled first
+    pub fn module_coupling(&self) -> Vec<(String, String, usize)> {
+        let calls: HashSet<(String, String)> = self.function_calls().into_iter().collect();
+        let mut coupling: HashMap<(String, String), usize> = HashMap::new();
+        for (caller, callee) in calls {
+            let (from, to) = (self.module_of(&caller), self.module_of(&callee));
+            let pair = match from.cmp(&to) {
+                std::cmp::Ordering::Less => (from, to),
+                std::cmp::Ordering::Greater => (to, from),
+                std::cmp::Ordering::Equal => continue,
+            };
+            *coupling.entry(pair).or_default() += 1;
+        }
+
+        let mut coupling: Vec<(String, String, usize)> = coupling
+            .into_iter()
+            .map(|((a, b), calls)| (a, b, calls))
+            .collect();
+        coupling.sort_by(|(a1, b1, calls1), (a2, b2, calls2)| {
+            calls2.cmp(calls1).then_with(|| (a1, b1).cmp(&(a2, b2)))
+        });
+        coupling
+    }
+
+    // One node per module, with an edge wherever a module's functions call another
+    // module's functions, weighted by how many such calls there are
+    pub fn collapse_to_modules(&self) -> Graph<String, usize, Directed> {
+        let module_calls: Vec<(String, String)> = self
+            .function_calls()
+            .iter()
+            .map(|(caller, callee)| (self.module_of(caller), self.module_of(callee)))
+            .collect();
+
+        let mut modules: Vec<&String> = module_calls.iter().flat_map(|(a, b)| [a, b]).collect();
+        modules.sort();
+        modules.dedup();
+
+        let mut graph = Graph::new();
+        let node_indices: HashMap<&String, NodeIndex> = modules
+            .into_iter()
+            .map(|module| (module, graph.add_node(module.clone())))
+            .collect();
+
+        let mut edge_indices = HashMap::new();
+        for (from, to) in &module_calls {
+            if from == to {
+                continue;
+            }
+            match edge_indices.get(&(from, to)) {
+                Some(&edge) => graph[edge] += 1,
+                None => {
+                    let edge = graph.add_edge(node_indices[from], node_indices[to], 1);
+                    edge_indices.insert((from, to), edge);
+                }
+            }
+        }
+
+        graph
+    }
+
+    // Pairs of distinct functions that call each other directly, each pair listed once
+    // with its names in sorted order
+    pub fn mutual_recursion_pairs(&self) -> Vec<(String, String)> {
+        let calls: HashSet<(String, String)> = self.function_calls().into_iter().collect();
+
+        let mut pairs: Vec<(String, String)> = calls
+            .iter()
+            .filter(|(caller, callee)| {
+                caller < callee && calls.contains(&(callee.clone(), caller.clone()))
+            })
+            .cloned()
+            .collect();
+        pairs.sort();
+        pairs
+    }
+
+    // Whether `to` can be reached from `from` by following calls (a function trivially
+    // reaches itself)
+    pub fn is_reachable(&self, from: &str, to: &str) -> bool {
+        reachable_from(&self.callees(), from).contains(to)
+    }
+
+    // How many other functions each function reaches by following calls, i.e. how much
+    // could be affected by changing it
+    pub fn reach_counts(&self) -> HashMap<String, usize> {
+        let callees = self.callees();
+        let functions: HashSet<String> = self
+            .function_calls()
+            .into_iter()
+            .flat_map(|(caller, callee)| [caller, callee])
+            .collect();
+
+        functions
+            .into_iter()
+            .map(|function| {
+                let reached = reachable_from(&callees, &function);
+                let count = reached.len() - 1;
+                (function, count)
+            })
+            .collect()
+    }
+
+    // The one function `name` refers to, either exactly or as the end of a qualified name
+    pub fn find_function(&self, name: &str) -> Result<String, Box<dyn Error>> {
+        let mut functions: Vec<String> = self
+            .functions()
+            .into_iter()
+            .map(|function| function.qualified_name)
+            .chain(
+                self.function_calls()
+                    .into_iter()
+                    .flat_map(|(caller, callee)| [caller, callee]),
+            )
+            .filter(|function| function == name || function.ends_with(&format!("::{}", name)))
+            .filter(|function| !self.is_initializer(function))
+            .collect();
+        functions.sort();
+        functions.dedup();
+
+        match functions.as_slice() {
+            [] => Err(format!("Function '{}' not found", name).into()),
+            [function] => Ok(function.clone()),
+            _ if functions.iter().any(|function| function == name) => Ok(name.to_string()),
+            _ => Err(format!(
+                "'{}' could be any of {}; qualify it further",
+                name,
+                functions.join(", ")
+            )
+            .into()),
+        }
+    }
+
+    // The functions calling `function` up to `hops` calls away, each with its distance,
+    // nearest first
+    pub fn callers_within(&self, function: &str, hops: usize) -> Vec<(String, usize)> {
+        let mut callers: HashMap<String, Vec<String>> = HashMap::new();
+        for (caller, callee) in self.function_calls() {
+            callers.entry(callee).or_default().push(caller);
+        }
+        within_hops(&callers, function, hops)
+    }
+
+    // The functions `function` calls up to `hops` calls away, each with its distance,
+    // nearest first
+    pub fn callees_within(&self, function: &str, hops: usize) -> Vec<(String, usize)> {
+        within_hops(&self.callees(), function, hops)
+    }
+
+    // The fewest calls leading from `from` to `to`, both included, if `to` can be reached
+    pub fn shortest_path(&self, from: &str, to: &str) -> Option<Vec<String>> {
+        let callees = self.callees();
+        let mut callers: HashMap<String, String> = HashMap::new();
+        let mut queue = VecDeque::from([from.to_string()]);
+        while let Some(function) = queue.pop_front() {
+            if function == to {
+                let mut path = vec![function];
+                while let Some(caller) = callers.get(path.last().unwrap()) {
+                    path.push(caller.clone());
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for callee in callees.get(&function).into_iter().flatten() {
+                if callee != from && !callers.contains_key(callee) {
+                    callers.insert(callee.clone(), function.clone());
+                    queue.push_back(callee.clone());
+                }
+            }
+        }
+        None
+    }
+
+    fn callees(&self) -> HashMap<String, Vec<String>> {
+        let mut callees: HashMap<String, Vec<String>> = HashMap::new();
+        for (caller, callee) in self.function_calls() {
+            callees.entry(caller).or_default().push(callee);
+        }
+        callees
+    }
+
+    // Each caller and what it calls, both sorted and without duplicates. Functions
+    // that call nothing are left out.
+    pub fn adjacency(&self) -> BTreeMap<String, Vec<String>> {
+        let mut adjacency: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (caller, callee) in self.function_calls() {
+            adjacency.entry(caller).or_default().push(callee);
+        }
+        for callees in adjacency.values_mut() {
+            callees.sort();
+            callees.dedup();
+        }
+        adjacency
+    }
+
+    pub fn get_entry_points(&self) -> Vec<String> {
+        let mut entry_points: Vec<String> = self
+            .visitors
+            .iter()
+            .flat_map(|visitor| {
+                visitor.functions.keys().filter(|name| {
+                    name.starts_with(&format!("{}::", visitor.crate_name))
+                        && name.ends_with("::main")
+                })
+            })
+            .cloned()
+            .collect();
+        entry_points.sort();
+        entry_points.dedup();
+        entry_points
+    }
+
+    // Every function calls were followed from: the start functions (or public API),
+    // tests, benches and initializers, sorted
+    pub fn followed_entries(&self) -> Vec<String> {
+        let mut entries: Vec<String> = self
+            .visitors
+            .iter()
+            .flat_map(|visitor| visitor.followed_entries.iter().cloned())
+            .collect();
+        entries.sort();
+        entries.dedup();
+        entries
+    }
+
+    // Every entry point with its kind: the `main` functions, then the tests, then the
+    // benches, each sorted
+    pub fn entry_points(&self) -> Vec<(String, EntryKind)> {
+        let mut entry_points: Vec<(String, EntryKind)> = self
+            .get_entry_points()
+            .into_iter()
+            .map(|name| (name, EntryKind::Main))
+            .chain(
+                self.test_entry_points
+                    .iter()
+                    .map(|name| (name.clone(), EntryKind::Test)),
+            )
+            .chain(
+                self.bench_entry_points
+                    .iter()
+                    .map(|name| (name.clone(), EntryKind::Bench)),
+            )
+            .collect();
+        entry_points.sort_by(|(a, a_kind), (b, b_kind)| a_kind.cmp(b_kind).then(a.cmp(b)));
+        entry_points.dedup();
+        entry_points
+    }
+
+    // Calls by path into a workspace crate that match none of its functions, sorted
+    pub fn unresolved_crate_calls(&self) -> Vec<(String, String)> {
+        let mut calls: Vec<(String, String)> = self
+            .visitors
+            .iter()
+            .flat_map(|visitor| visitor.unresolved_crate_calls.iter().cloned())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        calls.sort();
+        calls
+    }
+
+    // Calls from one crate's functions into another's, sorted by caller then callee,
+    // each listed once
+    pub fn get_cross_crate_calls(&self) -> Vec<(String, String)> {
+        let std_calls = self.std_calls();
+        let mut calls: Vec<(String, String)> = self
+            .function_calls()
+            .into_iter()
+            .filter(|(caller, callee)| utils::crate_of(caller) != utils::crate_of(callee))
+            .filter(|(_, callee)| !std_calls.contains(callee))
+            .collect();
+        calls.sort();
+        calls.dedup();
+        calls
+    }
+}
+
+// Every function reachable from `from` by following calls, `from` included
+fn reachable_from(callees: &HashMap<String, Vec<String>>, from: &str) -> HashSet<String> {
+    let mut visited = HashSet::from([from.to_string()]);
+    let mut queue = VecDeque::from([from.to_string()]);
+    while let Some(function) = queue.pop_front() {
+        for callee in callees.get(&function).into_iter().flatten() {
+            if visited.insert(callee.clone()) {
+                queue.push_back(callee.clone());
+            }
+        }
+    }
+    visited
+}
+
+// Every function at most `hops` steps from `from` in `edges`, with its distance, sorted by
+// distance and then name. `from` itself is left out.
+fn within_hops(
+    edges: &HashMap<String, Vec<String>>,
+    from: &str,
+    hops: usize,
+) -> Vec<(String, usize)> {
+    let mut distances = HashMap::from([(from.to_string(), 0)]);
+    let mut queue = VecDeque::from([from.to_string()]);
+    while let Some(function) = queue.pop_front() {
+        let distance = distances[&function];
+        if distance == hops {
+            continue;
+        }
+        for next in edges.get(&function).into_iter().flatten() {
+            if !distances.contains_key(next) {
+                distances.insert(next.clone(), distance + 1);
+                queue.push_back(next.clone());
+            }
+        }
+    }
+
+    let mut within: Vec<(String, usize)> = distances
+        .into_iter()
+        .filter(|(function, _)| function != from)
+        .collect();
+    within.sort_by(|(a, a_distance), (b, b_distance)| {
+        a_distance.cmp(b_distance).then_with(|| a.cmp(b))
+    });
+    within
+}
+
+pub fn analyze_repository(
+    dir: &Path,
+    config: &AnalysisConfig,
+) -> Result<WorkspaceAnalysis, Box<dyn Error>> {
+    let analyzer = WorkspaceAnalyzer::new(dir, config.clone())?;
+    analyzer.analyze_workspace()
+}
+
+// Independent repositories analyzed together as one workspace, so that calls from a
+// crate in one into a crate it depends on in another resolve
+pub fn analyze_repositories(
+    dirs: &[PathBuf],
+    config: &AnalysisConfig,
+) -> Result<WorkspaceAnalysis, Box<dyn Error>> {
+    let mut analyzer: Option<WorkspaceAnalyzer> = None;
+    for dir in dirs {
+        if !dir.join("Cargo.toml").is_file() {
+            return Err(format!("No Cargo.toml found in '{}'", dir.display()).into());
+        }
+        let repository = WorkspaceAnalyzer::new(dir, config.clone())?;
+        match &mut analyzer {
+            Some(analyzer) => analyzer.merge(repository),
+            None => analyzer = Some(repository),
+        }
+    }
+    match analyzer {
+        Some(analyzer) => analyzer.analyze_workspace(),
+        None => Err("No repositories to analyze".into()),
+    }
+}
+
+fn new_visitor(crate_name: &str, config: &AnalysisConfig) -> FunctionCallVisitor {
+    let mut visitor = FunctionCallVisitor::new(crate_name);
+    visitor.cache = config.cache_dir.clone().map(Cache::new);
+    visitor.include_std = config.include_std;
+    visitor.max_depth = config.max_depth;
+    visitor.only = config.only.clone();
+    visitor
+}
+
+// Analysis of a hand-picked set of files as one crate, for tools with their own idea
+// of which files belong together:
+//
+//     let analysis = AnalysisBuilder::new("mycrate")
+//         .add_file("src/server.rs")
+//         .add_file("src/handlers.rs")
+//         .add_start_function("serve")
+//         .build()?;
+pub struct AnalysisBuilder {
+    crate_name: String,
+    files: Vec<PathBuf>,
+    start_functions: Vec<String>,
+    config: AnalysisConfig,
+}
+
+impl AnalysisBuilder {
+    pub fn new(crate_name: &str) -> Self {
+        AnalysisBuilder {
+            crate_name: utils::normalize_crate_name(crate_name),
+            files: Vec::new(),
+            start_functions: Vec::new(),
+            config: AnalysisConfig::default(),
+        }
+    }
+
+    // Settings other than the start functions, which `add_start_function` sets
+    pub fn config(mut self, config: AnalysisConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    // Each file is a module named after it, except `main.rs` and `lib.rs` which are the
+    // crate root
+    pub fn add_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.files.push(path.into());
+        self
+    }
+
+    // Replaces the configured start functions, `main` by default
+    pub fn add_start_function(mut self, name: &str) -> Self {
+        self.start_functions.push(name.to_string());
+        self
+    }
+
+    pub fn build(mut self) -> Result<WorkspaceAnalysis, Box<dyn Error>> {
+        if !self.start_functions.is_empty() {
+            self.config.start_functions = Some(self.start_functions);
+        }
+
+        let mut visitor = new_visitor(&self.crate_name, &self.config);
+        for file in &self.files {
+            visitor.current_module = vec![self.crate_name.clone()];
+            visitor.process_module(file)?;
+        }
+        visitor.current_module.clear();
+
+        let fallback = public_fallback(&self.config, std::slice::from_ref(&visitor));
+        let (test_entry_points, bench_entry_points) =
+            split_entry_points(follow_entry_points(&self.config, &mut visitor, fallback));
+        warn_missing_start_functions(&self.config, std::slice::from_ref(&visitor));
+
+        Ok(WorkspaceAnalysis {
+            crate_names: vec![self.crate_name],
+            visitors: vec![visitor],
+            test_entry_points,
+            bench_entry_points,
+        })
+    }
+}
+
+// A lone `.rs` file, analyzed without any Cargo manifest. Its functions are prefixed
+// with the file's name as though it were a crate of that name.
+pub fn analyze_file(
+    file: &Path,
+    config: &AnalysisConfig,
+) -> Result<WorkspaceAnalysis, Box<dyn Error>> {
+    let name = utils::normalize_crate_name(&file.file_stem().unwrap_or_default().to_string_lossy());
+    log::info!("Analyzing file '{}'", file.display());
+
+    let mut visitor = new_visitor(&name, config);
+    // `process_module` roots everything but `main.rs`/`lib.rs` at the file name itself
+    if name == "main" || name == "lib" {
+        visitor.current_module = vec![name.clone()];
+    }
+    visitor.process_module(file)?;
+    visitor.current_module.clear();
+
+    let fallback = public_fallback(config, std::slice::from_ref(&visitor));
+    let (test_entry_points, bench_entry_points) =
+        split_entry_points(follow_entry_points(config, &mut visitor, fallback));
+    warn_missing_start_functions(config, std::slice::from_ref(&visitor));
+
+    Ok(WorkspaceAnalysis {
+        crate_names: vec![name],
+        visitors: vec![visitor],
+        test_entry_points,
+        bench_entry_points,
+    })
+}
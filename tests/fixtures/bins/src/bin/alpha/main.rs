@@ -0,0 +1,5 @@
+fn main() {
+    greet();
+}
+
+fn greet() {}
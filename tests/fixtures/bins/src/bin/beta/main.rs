@@ -0,0 +1,5 @@
+fn main() {
+    serve();
+}
+
+fn serve() {}
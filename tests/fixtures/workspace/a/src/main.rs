@@ -0,0 +1,10 @@
+fn main() {
+    run();
+    b::run();
+}
+
+fn run() {
+    helper();
+}
+
+fn helper() {}
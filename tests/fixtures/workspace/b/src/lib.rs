@@ -0,0 +1,9 @@
+pub fn run() {
+    handle();
+}
+
+pub fn handle() {
+    other();
+}
+
+fn other() {}
@@ -0,0 +1,61 @@
+use ferrisfollow::{
+    graph,
+    workspace::{self, AnalysisConfig},
+};
+use std::path::Path;
+
+fn calls(analysis: &workspace::WorkspaceAnalysis, caller: &str, callee: &str) -> bool {
+    analysis
+        .function_calls()
+        .iter()
+        .any(|(from, to)| from == caller && to == callee)
+}
+
+#[test]
+fn workspace_graph_keeps_crates_apart_and_calls_across_them() {
+    let analysis = workspace::analyze_repository(
+        Path::new("tests/fixtures/workspace"),
+        &AnalysisConfig::default(),
+    )
+    .unwrap();
+
+    assert!(calls(&analysis, "a::main", "a::run"));
+    assert!(calls(&analysis, "a::main", "b::run"));
+    assert!(calls(&analysis, "b::run", "b::handle"));
+
+    // Both crates have a `run`, which stay two nodes
+    let graph = graph::create_combined_graph(&analysis);
+    let runs = graph
+        .node_weights()
+        .filter(|name| name.as_str() == "a::run" || name.as_str() == "b::run")
+        .count();
+    assert_eq!(runs, 2);
+}
+
+#[test]
+fn each_binary_has_its_own_main() {
+    let analysis =
+        workspace::analyze_repository(Path::new("tests/fixtures/bins"), &AnalysisConfig::default())
+            .unwrap();
+
+    assert_eq!(
+        analysis.get_entry_points(),
+        vec!["bins::alpha::main", "bins::beta::main"]
+    );
+    assert!(calls(&analysis, "bins::alpha::main", "bins::alpha::greet"));
+    assert!(calls(&analysis, "bins::beta::main", "bins::beta::serve"));
+}
+
+#[test]
+fn reachability_follows_calls_in_their_direction() {
+    let analysis = workspace::analyze_repository(
+        Path::new("tests/fixtures/workspace"),
+        &AnalysisConfig::default(),
+    )
+    .unwrap();
+
+    assert!(analysis.is_reachable("a::main", "b::other"));
+    assert!(analysis.is_reachable("a::run", "a::run"));
+    assert!(!analysis.is_reachable("a::run", "b::run"));
+    assert!(!analysis.is_reachable("b::run", "a::main"));
+}